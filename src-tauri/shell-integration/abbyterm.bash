@@ -0,0 +1,64 @@
+# AbbyTerm shell integration for bash.
+#
+# Loaded with `bash --init-file`, which replaces the normal startup files, so
+# load the user's own files first and exactly as bash would have.
+
+if [ "${ABBYTERM_SHELL_LOGIN:-}" = "1" ]; then
+    unset ABBYTERM_SHELL_LOGIN
+    [ -r /etc/profile ] && . /etc/profile
+    if [ -r ~/.bash_profile ]; then
+        . ~/.bash_profile
+    elif [ -r ~/.bash_login ]; then
+        . ~/.bash_login
+    elif [ -r ~/.profile ]; then
+        . ~/.profile
+    fi
+else
+    [ -r ~/.bashrc ] && . ~/.bashrc
+fi
+
+# Only hook into interactive shells, and only once.
+if [[ $- != *i* || -n "${__abbyterm_integration_loaded:-}" ]]; then
+    return 0 2>/dev/null
+fi
+__abbyterm_integration_loaded=1
+__abbyterm_first_prompt=1
+
+__abbyterm_urlencode() {
+    local LC_ALL=C str="$1" out="" char i
+    for (( i = 0; i < ${#str}; i++ )); do
+        char="${str:i:1}"
+        case "$char" in
+            [a-zA-Z0-9/._~-]) out+="$char" ;;
+            *) printf -v char '%%%02X' "'$char"; out+="$char" ;;
+        esac
+    done
+    printf '%s' "$out"
+}
+
+# OSC 133;D closes the previous command with its exit status, OSC 7 reports
+# the cwd and OSC 133;A opens the new prompt. Runs first in PROMPT_COMMAND
+# so `$?` is still the status of the user's command.
+__abbyterm_prompt_start() {
+    local status=$?
+    if [ -z "$__abbyterm_first_prompt" ]; then
+        printf '\e]133;D;%s\a' "$status"
+    fi
+    __abbyterm_first_prompt=
+    printf '\e]7;file://%s%s\a' "${HOSTNAME:-localhost}" "$(__abbyterm_urlencode "$PWD")"
+    printf '\e]133;A\a'
+    return $status
+}
+
+# Runs last in PROMPT_COMMAND so prompt frameworks that rebuild PS1 every
+# time do not drop the end-of-prompt mark.
+__abbyterm_prompt_end() {
+    local status=$?
+    if [[ "$PS1" != *'133;B'* ]]; then
+        PS1="$PS1"'\[\e]133;B\a\]'
+    fi
+    return $status
+}
+
+PROMPT_COMMAND="__abbyterm_prompt_start${PROMPT_COMMAND:+;$PROMPT_COMMAND};__abbyterm_prompt_end"
+PS0="${PS0:-}"$'\e]133;C\a'
@@ -0,0 +1,42 @@
+# AbbyTerm shell integration for fish, found through XDG_DATA_DIRS.
+
+# Take our directory back out of XDG_DATA_DIRS so child processes never see it.
+if set -q ABBYTERM_SHELL_INTEGRATION_XDG_DIR
+    set -l dirs (string split : -- "$XDG_DATA_DIRS" | string match -v -- "$ABBYTERM_SHELL_INTEGRATION_XDG_DIR")
+    if test (count $dirs) -gt 0
+        set -gx XDG_DATA_DIRS (string join : -- $dirs)
+    else
+        set -e XDG_DATA_DIRS
+    end
+    set -e ABBYTERM_SHELL_INTEGRATION_XDG_DIR
+end
+
+status is-interactive; or exit
+set -q __abbyterm_integration_loaded; and exit
+set -g __abbyterm_integration_loaded 1
+
+function __abbyterm_osc7
+    printf '\e]7;file://%s%s\a' $hostname (string escape --style=url -- $PWD)
+end
+
+function __abbyterm_prompt_start --on-event fish_prompt
+    __abbyterm_osc7
+    printf '\e]133;A\a'
+
+    # config.fish defines fish_prompt after this file runs, so wrap it lazily.
+    if not functions -q __abbyterm_user_fish_prompt
+        functions -c fish_prompt __abbyterm_user_fish_prompt
+        function fish_prompt
+            __abbyterm_user_fish_prompt
+            printf '\e]133;B\a'
+        end
+    end
+end
+
+function __abbyterm_preexec --on-event fish_preexec
+    printf '\e]133;C\a'
+end
+
+function __abbyterm_postexec --on-event fish_postexec
+    printf '\e]133;D;%s\a' $status
+end
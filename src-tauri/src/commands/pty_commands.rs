@@ -1,6 +1,19 @@
-use crate::pty::manager::PtyManager;
+use crate::config::AppConfig;
+use crate::pty::manager::{PtyManager, PtySessionInfo};
+use crate::pty::monitor::MonitorSettings;
+use crate::pty::output::OutputChannels;
+use crate::pty::process::ProcessInfo;
+use crate::pty::signal::SignalTarget;
+use crate::pty::transcript::SessionKind;
+use crate::pty::triggers::TriggerRule;
+use crate::pty::unix_pty::{CellSize, SpawnOptions};
+use crate::pty::usage::ResourceUsage;
+use crate::remote::RemoteControl;
 use serde::Serialize;
-use std::time::UNIX_EPOCH;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::ipc::Channel;
 use tauri::{AppHandle, State};
 use uuid::Uuid;
 
@@ -12,6 +25,20 @@ pub struct ZmodemUploadFile {
     pub last_modified_ms: u64,
 }
 
+fn expand_tilde(path: &str) -> PathBuf {
+    if path.starts_with("~/") || path == "~" {
+        let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+        let expanded = if path == "~" {
+            home
+        } else {
+            path.replacen("~", &home, 1)
+        };
+        PathBuf::from(expanded)
+    } else {
+        PathBuf::from(path)
+    }
+}
+
 #[tauri::command]
 pub async fn create_ratel_session(
     host: String,
@@ -27,40 +54,77 @@ pub async fn create_ratel_session(
     let addr = format!("{}:{}", host.trim(), port);
     let args = vec!["--ratel".to_string(), addr];
 
+    let options = SpawnOptions {
+        shell: Some(exe),
+        args: Some(args),
+        cols,
+        rows,
+        env_filter: AppConfig::load().environment,
+        ..Default::default()
+    };
     let id = manager
-        .create_session(Some(exe), Some(args), None, cols, rows, app)
+        .create_session(options, app.into())
         .await
         .map_err(|e| e.to_string())?;
 
     Ok(id.to_string())
 }
 
+/// Options for a shell opened from the UI or with `abbyterm @ new-tab`,
+/// with the backend config applied.
+pub fn session_options(
+    shell: Option<String>,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+    cols: u16,
+    rows: u16,
+    log: Option<bool>,
+) -> SpawnOptions {
+    let config = AppConfig::load();
+    // An explicit `log` overrides the per-tab-type setting.
+    let kind = SessionKind::of_shell(shell.as_deref());
+    let transcript = log
+        .unwrap_or_else(|| config.logging.enabled_for(kind))
+        .then_some(config.logging);
+    SpawnOptions {
+        shell,
+        args,
+        cwd: cwd.map(|path| expand_tilde(&path)),
+        cols,
+        rows,
+        env_filter: config.environment,
+        shell_integration: config.shell_integration.enabled,
+        transcript,
+        triggers: config.triggers,
+        ..Default::default()
+    }
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_pty_session(
     shell: Option<String>,
     args: Option<Vec<String>>,
     cwd: Option<String>,
     cols: u16,
     rows: u16,
+    cell_width: Option<u16>,
+    cell_height: Option<u16>,
+    env: Option<HashMap<String, String>>,
+    unset_env: Option<Vec<String>>,
+    log: Option<bool>,
     manager: State<'_, PtyManager>,
+    remote: State<'_, RemoteControl>,
     app: AppHandle,
 ) -> Result<String, String> {
-    // Expand tilde in cwd path
-    let cwd_path = cwd.map(|path| {
-        if path.starts_with("~/") || path == "~" {
-            let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-            let expanded = if path == "~" {
-                home
-            } else {
-                path.replacen("~", &home, 1)
-            };
-            std::path::PathBuf::from(expanded)
-        } else {
-            std::path::PathBuf::from(path)
-        }
-    });
-    let id = manager
-        .create_session(shell, args, cwd_path, cols, rows, app)
+    let options = SpawnOptions {
+        cell: cell_size(cell_width, cell_height).unwrap_or_default(),
+        env: env.unwrap_or_default(),
+        unset_env: unset_env.unwrap_or_default(),
+        ..session_options(shell, args, cwd, cols, rows, log)
+    };
+    let id = remote
+        .create_session(&manager, options, app.into())
         .await
         .map_err(|e| e.to_string())?;
     Ok(id.to_string())
@@ -89,24 +153,289 @@ pub async fn pty_write_bytes(
     manager.write(id, &data).await.map_err(|e| e.to_string())
 }
 
+/// Synchronizes the sessions' input: a `pty_write` to any of them is sent
+/// to all. Returns the group id.
+#[tauri::command]
+pub async fn create_input_group(
+    session_ids: Vec<String>,
+    manager: State<'_, PtyManager>,
+) -> Result<String, String> {
+    let ids = session_ids
+        .iter()
+        .map(|id| Uuid::parse_str(id))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let group = manager
+        .create_input_group(ids)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(group.to_string())
+}
+
+#[tauri::command]
+pub async fn add_to_group(
+    group_id: String,
+    session_id: String,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let group = Uuid::parse_str(&group_id).map_err(|e| e.to_string())?;
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .add_to_group(group, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_from_group(
+    group_id: String,
+    session_id: String,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let group = Uuid::parse_str(&group_id).map_err(|e| e.to_string())?;
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .remove_from_group(group, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// The cell size in pixels, when the frontend knows both dimensions.
+fn cell_size(width: Option<u16>, height: Option<u16>) -> Option<CellSize> {
+    width
+        .zip(height)
+        .map(|(width, height)| CellSize { width, height })
+}
+
+/// Bursts of resizes are coalesced by the session, so this can be called
+/// on every layout change.
 #[tauri::command]
 pub async fn pty_resize(
     session_id: String,
     cols: u16,
     rows: u16,
+    cell_width: Option<u16>,
+    cell_height: Option<u16>,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .resize(id, cols, rows, cell_size(cell_width, cell_height))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Grace period between SIGHUP and SIGKILL for a plain `pty_kill`.
+const KILL_GRACE_MS: u64 = 250;
+/// Default grace period when closing with `graceful: true`.
+const GRACEFUL_KILL_TIMEOUT_MS: u64 = 3000;
+
+#[tauri::command]
+pub async fn pty_kill(
+    session_id: String,
+    graceful: Option<bool>,
+    timeout_ms: Option<u64>,
+    manager: State<'_, PtyManager>,
+    channels: State<'_, OutputChannels>,
+    remote: State<'_, RemoteControl>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    channels.detach(id);
+    remote.forget(id);
+    let grace_ms = if graceful.unwrap_or(false) {
+        timeout_ms.unwrap_or(GRACEFUL_KILL_TIMEOUT_MS)
+    } else {
+        KILL_GRACE_MS
+    };
+    manager
+        .kill(id, Duration::from_millis(grace_ms))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn pty_signal(
+    session_id: String,
+    signal: String,
+    target: Option<SignalTarget>,
     manager: State<'_, PtyManager>,
 ) -> Result<(), String> {
     let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
     manager
-        .resize(id, cols, rows)
+        .signal(id, &signal, target.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn start_recording(
+    session_id: String,
+    path: String,
+    record_input: Option<bool>,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .start_recording(id, &expand_tilde(&path), record_input.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn stop_recording(
+    session_id: String,
+    manager: State<'_, PtyManager>,
+) -> Result<Option<String>, String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager.stop_recording(id).await.map_err(|e| e.to_string())
+}
+
+/// Starts a plain-text transcript using the `[logging]` settings and
+/// returns the file it is written to.
+#[tauri::command]
+pub async fn start_transcript(
+    session_id: String,
+    manager: State<'_, PtyManager>,
+) -> Result<String, String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .start_transcript(id, &AppConfig::load().logging)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn pty_kill(session_id: String, manager: State<'_, PtyManager>) -> Result<(), String> {
+pub async fn stop_transcript(
+    session_id: String,
+    manager: State<'_, PtyManager>,
+) -> Result<Option<String>, String> {
     let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
-    manager.kill(id).await.map_err(|e| e.to_string())
+    manager.stop_transcript(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_playback_session(
+    path: String,
+    speed: Option<f64>,
+    manager: State<'_, PtyManager>,
+    app: AppHandle,
+) -> Result<String, String> {
+    let id = manager
+        .create_playback(&expand_tilde(&path), speed.unwrap_or(1.0), app)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(id.to_string())
+}
+
+#[tauri::command]
+pub async fn playback_set_paused(
+    session_id: String,
+    paused: bool,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .control_playback(id, |playback| playback.set_paused(paused))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// `position` is in seconds of recording time.
+#[tauri::command]
+pub async fn playback_seek(
+    session_id: String,
+    position: f64,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .control_playback(id, |playback| playback.seek(position))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn playback_set_speed(
+    session_id: String,
+    speed: f64,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .control_playback(id, |playback| playback.set_speed(speed))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Streams the session's output to `on_output` as binary messages. With
+/// `replay`, the scrollback is sent first, so a terminal adopting a running
+/// session starts from its recent output. Attaching again replaces the
+/// channel. The terminal must acknowledge what it renders with
+/// `pty_ack_output`.
+#[tauri::command]
+pub async fn attach_pty_output(
+    session_id: String,
+    replay: Option<bool>,
+    on_output: Channel,
+    manager: State<'_, PtyManager>,
+    channels: State<'_, OutputChannels>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .attach_output(id, on_output, replay.unwrap_or(false), &channels)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Acknowledges that the terminal rendered `bytes` of the session's
+/// output. Reading from the PTY pauses while too much is unacknowledged.
+#[tauri::command]
+pub async fn pty_ack_output(
+    session_id: String,
+    bytes: usize,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .ack_output(id, bytes)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tells the backend whether the session's tab is shown. Output of a
+/// hidden session is held back and trimmed to its tail, then sent in one
+/// batch once it is shown again; `session-activity` keeps firing, and the
+/// scrollback keeps everything. Full-screen programs and ZMODEM transfers
+/// keep streaming.
+#[tauri::command]
+pub async fn set_session_visibility(
+    session_id: String,
+    visible: bool,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .set_visibility(id, visible)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn pty_replay(
+    session_id: String,
+    manager: State<'_, PtyManager>,
+) -> Result<Vec<u8>, String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager.replay(id).await.map_err(|e| e.to_string())
+}
+
+/// Every session the backend holds, including ones whose child has exited
+/// but that were not closed yet.
+#[tauri::command]
+pub async fn list_pty_sessions(
+    manager: State<'_, PtyManager>,
+) -> Result<Vec<PtySessionInfo>, String> {
+    manager.list().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -118,6 +447,99 @@ pub async fn get_session_cwd(
     manager.get_cwd(id).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn get_session_foreground(
+    session_id: String,
+    manager: State<'_, PtyManager>,
+) -> Result<ProcessInfo, String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager.get_foreground(id).await.map_err(|e| e.to_string())
+}
+
+/// Default and minimum period of `session-resource-usage` events.
+const USAGE_INTERVAL_MS: u64 = 2000;
+const MIN_USAGE_INTERVAL_MS: u64 = 250;
+
+#[tauri::command]
+pub async fn get_session_resource_usage(
+    session_id: String,
+    manager: State<'_, PtyManager>,
+) -> Result<ResourceUsage, String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager.resource_usage(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn subscribe_session_resource_usage(
+    session_id: String,
+    interval_ms: Option<u64>,
+    manager: State<'_, PtyManager>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    let interval_ms = interval_ms
+        .unwrap_or(USAGE_INTERVAL_MS)
+        .max(MIN_USAGE_INTERVAL_MS);
+    manager
+        .subscribe_resource_usage(id, Duration::from_millis(interval_ms), app.into())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn unsubscribe_session_resource_usage(
+    session_id: String,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .unsubscribe_resource_usage(id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Shortest accepted activity/silence threshold; below it every keystroke
+/// echo would count.
+const MIN_MONITOR_MS: u64 = 500;
+
+/// Sets the quiet periods after which the session emits `session-activity`
+/// (output after being quiet) and `session-silence` (no output). Omitted
+/// thresholds turn the event off.
+#[tauri::command]
+pub async fn set_session_monitor(
+    session_id: String,
+    activity_after_ms: Option<u64>,
+    silence_after_ms: Option<u64>,
+    manager: State<'_, PtyManager>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    let settings = MonitorSettings {
+        activity_after_ms: activity_after_ms.map(|ms| ms.max(MIN_MONITOR_MS)),
+        silence_after_ms: silence_after_ms.map(|ms| ms.max(MIN_MONITOR_MS)),
+    };
+    manager
+        .set_monitor(id, settings, app.into())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Replaces the session's output triggers; matches are reported as
+/// `session-trigger` events. Sessions start with the `[[triggers]]` from
+/// the config file.
+#[tauri::command]
+pub async fn set_session_triggers(
+    session_id: String,
+    triggers: Vec<TriggerRule>,
+    manager: State<'_, PtyManager>,
+) -> Result<(), String> {
+    let id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
+    manager
+        .set_triggers(id, triggers)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn prepare_zmodem_upload_files(
     paths: Vec<String>,
@@ -0,0 +1,96 @@
+use crate::daemon::DaemonConfig;
+use crate::pty::env::EnvironmentConfig;
+use crate::pty::shell_integration::ShellIntegrationConfig;
+use crate::pty::transcript::LoggingConfig;
+use crate::pty::triggers::TriggerRule;
+use crate::remote::RemoteControlConfig;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Backend settings read from `~/.abbyterm/config.toml`. Every section is
+/// optional; a missing or unreadable file yields the defaults.
+///
+/// ```toml
+/// [environment]
+/// scrub = true
+/// deny = ["MY_LAUNCHER_*"]
+/// allow = ["LD_LIBRARY_PATH"]
+///
+/// [shell_integration]
+/// enabled = true
+///
+/// [logging]
+/// ssh = true
+/// timestamps = true
+/// max_file_size = 10485760
+/// max_files = 5
+///
+/// [daemon]
+/// enabled = false
+///
+/// [remote_control]
+/// enabled = true
+/// require_token = false
+///
+/// [[triggers]]
+/// pattern = "BUILD FAILED"
+///
+/// [[triggers]]
+/// pattern = "Press RETURN to continue"
+/// respond = "\r"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub environment: EnvironmentConfig,
+    pub shell_integration: ShellIntegrationConfig,
+    pub logging: LoggingConfig,
+    pub daemon: DaemonConfig,
+    pub remote_control: RemoteControlConfig,
+    pub triggers: Vec<TriggerRule>,
+}
+
+impl AppConfig {
+    /// Reads the config file on every call so edits apply to the next
+    /// session without restarting the app.
+    pub fn load() -> Self {
+        let Some(path) = config_path() else {
+            return Self::default();
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => return Self::default(),
+        };
+
+        toml::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("Ignoring invalid {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+}
+
+/// `~/.abbyterm`, the directory holding sessions, config and other app data.
+pub fn abbyterm_dir() -> Option<PathBuf> {
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("XDG_CONFIG_HOME").map(PathBuf::from))
+        .ok()
+        .map(|dir| dir.join(".abbyterm"))
+}
+
+/// Creates `dir` so only its owner can enter it, tightening it if it
+/// already exists. A socket bound inside can't be reached by anyone else,
+/// not even before its own mode is restricted.
+#[cfg(unix)]
+pub fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+}
+
+fn config_path() -> Option<PathBuf> {
+    abbyterm_dir().map(|dir| dir.join("config.toml"))
+}
@@ -0,0 +1,161 @@
+use super::protocol::{DaemonMessage, Request, RequestFrame};
+use super::socket_path;
+use crate::pty::output::OutputChannels;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::UnixStream;
+use tokio::sync::oneshot;
+
+/// How long to wait for a freshly spawned daemon to start listening.
+const DAEMON_STARTUP_TIMEOUT: Duration = Duration::from_secs(3);
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+/// The GUI's connection to the session daemon. Requests are answered over
+/// the same socket; session events pushed by the daemon are re-emitted to
+/// the webview unchanged and output goes to the terminals' channels, so the
+/// frontend can't tell the difference.
+pub struct DaemonClient {
+    writer: tokio::sync::Mutex<OwnedWriteHalf>,
+    pending: Pending,
+    next_id: AtomicU64,
+}
+
+impl DaemonClient {
+    /// Connects to the running daemon, starting one if there is none.
+    pub async fn connect(app: AppHandle) -> Result<Self> {
+        let path = socket_path().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+
+        let stream = match UnixStream::connect(&path).await {
+            Ok(stream) => stream,
+            Err(_) => {
+                spawn_daemon()?;
+                wait_for_socket(&path).await?
+            }
+        };
+
+        let (read, write) = stream.into_split();
+        let pending = Pending::default();
+
+        let reader_pending = pending.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str(&line) {
+                    Ok(DaemonMessage::Event { event, payload }) => {
+                        let _ = app.emit(&event, payload);
+                    }
+                    Ok(DaemonMessage::Output { id, data }) => {
+                        if let Some(channels) = app.try_state::<OutputChannels>() {
+                            channels.send(id, &data);
+                        }
+                    }
+                    Ok(DaemonMessage::Replay { id, data }) => {
+                        if let Some(channels) = app.try_state::<OutputChannels>() {
+                            channels.replay(id, data);
+                        }
+                    }
+                    Ok(DaemonMessage::Response { id, result }) => {
+                        let waiter = reader_pending.lock().ok().and_then(|mut p| p.remove(&id));
+                        if let Some(waiter) = waiter {
+                            let _ = waiter.send(result);
+                        }
+                    }
+                    Err(err) => eprintln!("Ignoring malformed daemon message: {}", err),
+                }
+            }
+
+            eprintln!("Lost connection to the session daemon");
+            // Dropping the senders fails every request still in flight.
+            if let Ok(mut pending) = reader_pending.lock() {
+                pending.clear();
+            }
+        });
+
+        Ok(Self {
+            writer: tokio::sync::Mutex::new(write),
+            pending,
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    pub async fn call(&self, request: Request) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(id, sender);
+        }
+
+        let mut line = serde_json::to_string(&RequestFrame { id, request })?;
+        line.push('\n');
+        if let Err(err) = self.writer.lock().await.write_all(line.as_bytes()).await {
+            if let Ok(mut pending) = self.pending.lock() {
+                pending.remove(&id);
+            }
+            return Err(anyhow::anyhow!("Session daemon unavailable: {}", err));
+        }
+
+        match receiver.await {
+            Ok(result) => result.map_err(|e| anyhow::anyhow!(e)),
+            Err(_) => Err(anyhow::anyhow!("Session daemon disconnected")),
+        }
+    }
+
+    pub async fn call_as<T: DeserializeOwned>(&self, request: Request) -> Result<T> {
+        Ok(serde_json::from_value(self.call(request).await?)?)
+    }
+}
+
+/// Starts `abbyterm --daemon` in its own session, so it is not hung up
+/// along with the window's process group.
+fn spawn_daemon() -> Result<()> {
+    let exe = std::env::current_exe()?;
+    let log = socket_path()
+        .and_then(|path| path.parent().map(|dir| dir.join("daemon.log")))
+        .and_then(|path| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .ok()
+        });
+
+    let mut command = std::process::Command::new(exe);
+    command
+        .arg("--daemon")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(log.map(Stdio::from).unwrap_or_else(Stdio::null));
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    command.spawn()?;
+    Ok(())
+}
+
+async fn wait_for_socket(path: &Path) -> Result<UnixStream> {
+    let deadline = tokio::time::Instant::now() + DAEMON_STARTUP_TIMEOUT;
+    loop {
+        match UnixStream::connect(path).await {
+            Ok(stream) => return Ok(stream),
+            Err(err) if tokio::time::Instant::now() >= deadline => {
+                return Err(anyhow::anyhow!("Session daemon did not start: {}", err));
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(unix)]
+pub mod client;
+pub mod protocol;
+#[cfg(unix)]
+pub mod server;
+
+use crate::config::abbyterm_dir;
+use protocol::DaemonMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use uuid::Uuid;
+
+#[cfg(unix)]
+pub use client::DaemonClient;
+#[cfg(unix)]
+pub use server::run_daemon;
+
+/// `[daemon]` section of `~/.abbyterm/config.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// Run sessions in a background daemon that outlives the window. The
+    /// daemon is started on demand and exits once it has neither sessions
+    /// nor connected windows.
+    pub enabled: bool,
+}
+
+pub fn socket_path() -> Option<PathBuf> {
+    abbyterm_dir().map(|dir| dir.join("daemon.sock"))
+}
+
+/// Messages a window may fall behind by. Output is flow controlled only
+/// for the window that acknowledges it; another window that stops reading
+/// this far behind is disconnected instead of queueing without limit.
+const CLIENT_QUEUE_CAPACITY: usize = 1024;
+
+/// Fans daemon messages out to connected clients. Each client gets its own
+/// queue, drained by a writer task, so a slow window never stalls the PTY
+/// output threads.
+#[derive(Clone, Default)]
+pub struct Broadcaster {
+    clients: Arc<Mutex<HashMap<u64, Sender<String>>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Broadcaster {
+    /// The queue ends when the client is dropped for falling behind.
+    pub fn subscribe(&self) -> (u64, Receiver<String>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.insert(id, sender);
+        }
+        (id, receiver)
+    }
+
+    pub fn unsubscribe(&self, id: u64) {
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.remove(&id);
+        }
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients
+            .lock()
+            .map(|clients| clients.len())
+            .unwrap_or(0)
+    }
+
+    pub fn emit<S: Serialize>(&self, event: &str, payload: &S) {
+        self.broadcast(|| DaemonMessage::Event {
+            event: event.to_string(),
+            payload: serde_json::to_value(payload).unwrap_or_default(),
+        });
+    }
+
+    pub fn output(&self, id: Uuid, data: &[u8]) {
+        self.broadcast(|| DaemonMessage::Output {
+            id,
+            data: data.to_vec(),
+        });
+    }
+
+    /// Builds the message only if a client is connected.
+    fn broadcast(&self, message: impl FnOnce() -> DaemonMessage) {
+        let Ok(mut clients) = self.clients.lock() else {
+            return;
+        };
+        if clients.is_empty() {
+            return;
+        }
+
+        let Some(line) = message().to_line() else {
+            return;
+        };
+        clients.retain(|&id, sender| queue(id, sender, line.clone()));
+    }
+
+    pub fn send_to(&self, id: u64, message: &DaemonMessage) {
+        let Some(line) = message.to_line() else {
+            return;
+        };
+        if let Ok(mut clients) = self.clients.lock() {
+            if let Some(sender) = clients.get(&id) {
+                if !queue(id, sender, line) {
+                    clients.remove(&id);
+                }
+            }
+        }
+    }
+}
+
+/// Whether the client is still connected and keeping up.
+fn queue(id: u64, sender: &Sender<String>, line: String) -> bool {
+    match sender.try_send(line) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            eprintln!("Disconnecting daemon client {}: it stopped reading", id);
+            false
+        }
+        Err(TrySendError::Closed(_)) => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn run_daemon() -> anyhow::Result<()> {
+    anyhow::bail!("The session daemon is not supported on this platform")
+}
+
+/// Stand-in so `PtyManager` can hold an optional client on every platform;
+/// it is never constructed where Unix sockets are unavailable.
+#[cfg(not(unix))]
+pub struct DaemonClient;
+
+#[cfg(not(unix))]
+impl DaemonClient {
+    pub async fn call_as<T: serde::de::DeserializeOwned>(
+        &self,
+        _request: protocol::Request,
+    ) -> anyhow::Result<T> {
+        anyhow::bail!("The session daemon is not supported on this platform")
+    }
+}
@@ -0,0 +1,219 @@
+use crate::pty::monitor::MonitorSettings;
+use crate::pty::signal::SignalTarget;
+use crate::pty::transcript::LoggingConfig;
+use crate::pty::triggers::TriggerRule;
+use crate::pty::unix_pty::{CellSize, SpawnOptions};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// A `PtyManager` operation, forwarded from the GUI to the daemon.
+///
+/// The protocol is newline-delimited JSON: the GUI sends [`RequestFrame`]s,
+/// the daemon answers each with a [`DaemonMessage::Response`] and pushes
+/// every session event to all connected clients as a
+/// [`DaemonMessage::Event`], and output as a [`DaemonMessage::Output`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    Create {
+        options: Box<SpawnOptions>,
+    },
+    Write {
+        id: Uuid,
+        data: Vec<u8>,
+    },
+    Resize {
+        id: Uuid,
+        cols: u16,
+        rows: u16,
+        cell: Option<CellSize>,
+    },
+    Kill {
+        id: Uuid,
+        grace_ms: u64,
+    },
+    Signal {
+        id: Uuid,
+        signal: String,
+        target: SignalTarget,
+    },
+    GetCwd {
+        id: Uuid,
+    },
+    GetForeground {
+        id: Uuid,
+    },
+    GetResourceUsage {
+        id: Uuid,
+    },
+    SubscribeResourceUsage {
+        id: Uuid,
+        interval_ms: u64,
+    },
+    UnsubscribeResourceUsage {
+        id: Uuid,
+    },
+    SetMonitor {
+        id: Uuid,
+        settings: MonitorSettings,
+    },
+    SetTriggers {
+        id: Uuid,
+        triggers: Vec<TriggerRule>,
+    },
+    StartFlowControl {
+        id: Uuid,
+    },
+    AckOutput {
+        id: Uuid,
+        bytes: usize,
+    },
+    SetVisibility {
+        id: Uuid,
+        visible: bool,
+    },
+    StartRecording {
+        id: Uuid,
+        path: PathBuf,
+        record_input: bool,
+    },
+    StopRecording {
+        id: Uuid,
+    },
+    StartTranscript {
+        id: Uuid,
+        config: LoggingConfig,
+    },
+    StopTranscript {
+        id: Uuid,
+    },
+    CreateInputGroup {
+        ids: Vec<Uuid>,
+    },
+    AddToGroup {
+        group: Uuid,
+        id: Uuid,
+    },
+    RemoveFromGroup {
+        group: Uuid,
+        id: Uuid,
+    },
+    Replay {
+        id: Uuid,
+    },
+    /// Starts flow control for the requesting window, sending it the
+    /// scrollback first as a [`DaemonMessage::Replay`], in line with the
+    /// session's output.
+    AttachOutput {
+        id: Uuid,
+    },
+    List,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RequestFrame {
+    pub id: u64,
+    pub request: Request,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonMessage {
+    /// Answer to the request with the same `id`.
+    Response {
+        id: u64,
+        result: Result<Value, String>,
+    },
+    /// A session event, re-emitted by the GUI under the same name.
+    Event { event: String, payload: Value },
+    /// Session output, for the session's terminal channel.
+    Output {
+        id: Uuid,
+        #[serde(with = "base64")]
+        data: Vec<u8>,
+    },
+    /// The scrollback a window asked for with `AttachOutput`. Output
+    /// before it is part of it.
+    Replay {
+        id: Uuid,
+        #[serde(with = "base64")]
+        data: Vec<u8>,
+    },
+}
+
+impl DaemonMessage {
+    pub fn to_line(&self) -> Option<String> {
+        serde_json::to_string(self).ok().map(|json| json + "\n")
+    }
+}
+
+/// Output is sent base64-encoded: as a JSON array of numbers it would take
+/// about four times the space.
+mod base64 {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        decode(&text).ok_or_else(|| D::Error::custom("invalid base64 data"))
+    }
+
+    fn encode(data: &[u8]) -> String {
+        let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+        for chunk in data.chunks(3) {
+            let byte = |index: usize| u32::from(chunk.get(index).copied().unwrap_or(0));
+            let group = byte(0) << 16 | byte(1) << 8 | byte(2);
+            for index in 0..4 {
+                if index <= chunk.len() {
+                    text.push(ALPHABET[(group >> (18 - 6 * index) & 63) as usize] as char);
+                } else {
+                    text.push('=');
+                }
+            }
+        }
+        text
+    }
+
+    fn decode(text: &str) -> Option<Vec<u8>> {
+        let text = text.as_bytes();
+        if !text.len().is_multiple_of(4) {
+            return None;
+        }
+        let groups = text.len() / 4;
+        let mut data = Vec::with_capacity(groups * 3);
+        for (index, chunk) in text.chunks(4).enumerate() {
+            let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+            // Only the last group may be padded, and by two at most.
+            if padding > 2 || (padding > 0 && index + 1 < groups) {
+                return None;
+            }
+            let mut group = 0;
+            for &c in &chunk[..4 - padding] {
+                group = group << 6 | value(c)?;
+            }
+            group <<= 6 * padding;
+            data.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+        }
+        Some(data)
+    }
+
+    fn value(c: u8) -> Option<u32> {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        Some(u32::from(value))
+    }
+}
@@ -0,0 +1,254 @@
+use super::protocol::{DaemonMessage, Request, RequestFrame};
+use super::{socket_path, Broadcaster};
+use crate::config::create_private_dir;
+use crate::pty::events::EventSink;
+use crate::pty::input::INPUT_QUEUE_CAPACITY;
+use crate::pty::manager::PtyManager;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// How often an idle daemon checks whether it can exit.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Entry point of `abbyterm --daemon`. Owns the PTYs so shells outlive the
+/// window, and serves them to GUI instances over `~/.abbyterm/daemon.sock`.
+/// Exits once no window is connected and every session has ended.
+pub fn run_daemon() -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(serve())
+}
+
+async fn serve() -> Result<()> {
+    let path = socket_path().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    let listener = bind(&path).await?;
+
+    let manager = Arc::new(PtyManager::new());
+    let broadcaster = Broadcaster::default();
+    let mut idle_check = tokio::time::interval(IDLE_CHECK_INTERVAL);
+    // The first tick completes immediately; give the window that started
+    // us a full interval to connect.
+    idle_check.tick().await;
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_client(stream, manager.clone(), broadcaster.clone()));
+                }
+                Err(err) => eprintln!("Daemon accept failed: {}", err),
+            },
+            _ = idle_check.tick() => {
+                if broadcaster.client_count() == 0 && !manager.has_live_sessions().await {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Binds the socket, replacing a stale one left by a daemon that died. Only
+/// the owner may connect: whoever can reach the socket controls the shells.
+async fn bind(path: &Path) -> Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(anyhow::anyhow!(
+                "A daemon is already listening on {}",
+                path.display()
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        create_private_dir(parent)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+async fn handle_client(stream: UnixStream, manager: Arc<PtyManager>, broadcaster: Broadcaster) {
+    let (read, mut write) = stream.into_split();
+    let (client_id, mut outgoing) = broadcaster.subscribe();
+
+    let mut writer = tokio::spawn(async move {
+        while let Some(line) = outgoing.recv().await {
+            if write.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(read).lines();
+
+    // Writes wait for room in their session's input queue, so they go
+    // through a task per session instead; a session that stops reading
+    // its input then doesn't hold up this window's other requests. Once
+    // its writer has a full queue too, further writes fail.
+    let mut writers: HashMap<Uuid, mpsc::Sender<(u64, Vec<u8>)>> = HashMap::new();
+
+    // Requests are handled in order so keystrokes reach the PTY in the
+    // order they were typed. The writer ends when the window is dropped
+    // for falling behind, which closes the connection.
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line,
+            _ = &mut writer => break,
+        };
+        let Ok(Some(line)) = line else {
+            break;
+        };
+        let (id, result) = match serde_json::from_str::<RequestFrame>(&line) {
+            Ok(RequestFrame {
+                id,
+                request: Request::Write { id: session, data },
+            }) => {
+                let writer = writers.entry(session).or_insert_with(|| {
+                    spawn_writer(session, client_id, manager.clone(), broadcaster.clone())
+                });
+                match writer.try_send((id, data)) {
+                    Ok(()) => continue,
+                    Err(_) => (id, Err("Session is not reading its input".to_string())),
+                }
+            }
+            Ok(frame) => {
+                if let Request::Kill { id, .. } = &frame.request {
+                    // Lets the session's writer finish what it has queued.
+                    writers.remove(id);
+                }
+                let result = dispatch(&manager, &broadcaster, client_id, frame.request).await;
+                (frame.id, result.map_err(|e| e.to_string()))
+            }
+            // Still answer requests we don't understand, e.g. from a newer
+            // GUI, so the caller doesn't wait forever.
+            Err(err) => match serde_json::from_str::<Value>(&line)
+                .ok()
+                .and_then(|value| value.get("id").and_then(Value::as_u64))
+            {
+                Some(id) => (id, Err(format!("Unsupported daemon request: {}", err))),
+                None => {
+                    eprintln!("Ignoring malformed daemon request: {}", err);
+                    continue;
+                }
+            },
+        };
+
+        broadcaster.send_to(client_id, &DaemonMessage::Response { id, result });
+    }
+
+    broadcaster.unsubscribe(client_id);
+    writer.abort();
+    // Acknowledgements came from windows; without any, output must flow.
+    if broadcaster.client_count() == 0 {
+        manager.release_flow_control().await;
+    }
+}
+
+/// Writes the input one window sends to one session, in order, answering
+/// each write once it is queued.
+fn spawn_writer(
+    session: Uuid,
+    client_id: u64,
+    manager: Arc<PtyManager>,
+    broadcaster: Broadcaster,
+) -> mpsc::Sender<(u64, Vec<u8>)> {
+    let (sender, mut receiver) = mpsc::channel::<(u64, Vec<u8>)>(INPUT_QUEUE_CAPACITY);
+    tokio::spawn(async move {
+        while let Some((id, data)) = receiver.recv().await {
+            let result = manager
+                .write(session, &data)
+                .await
+                .map(|()| Value::Null)
+                .map_err(|e| e.to_string());
+            broadcaster.send_to(client_id, &DaemonMessage::Response { id, result });
+        }
+    });
+    sender
+}
+
+async fn dispatch(
+    manager: &PtyManager,
+    broadcaster: &Broadcaster,
+    client_id: u64,
+    request: Request,
+) -> Result<Value> {
+    let events = EventSink::Daemon(broadcaster.clone());
+    Ok(match request {
+        Request::Create { options } => {
+            json!(manager.create_session(*options, events.clone()).await?)
+        }
+        Request::Write { id, data } => json!(manager.write(id, &data).await?),
+        Request::Resize {
+            id,
+            cols,
+            rows,
+            cell,
+        } => json!(manager.resize(id, cols, rows, cell).await?),
+        Request::Kill { id, grace_ms } => {
+            json!(manager.kill(id, Duration::from_millis(grace_ms)).await?)
+        }
+        Request::Signal { id, signal, target } => {
+            json!(manager.signal(id, &signal, target).await?)
+        }
+        Request::GetCwd { id } => json!(manager.get_cwd(id).await?),
+        Request::GetForeground { id } => json!(manager.get_foreground(id).await?),
+        Request::GetResourceUsage { id } => json!(manager.resource_usage(id).await?),
+        Request::SubscribeResourceUsage { id, interval_ms } => {
+            let interval = Duration::from_millis(interval_ms);
+            json!(
+                manager
+                    .subscribe_resource_usage(id, interval, events.clone())
+                    .await?
+            )
+        }
+        Request::UnsubscribeResourceUsage { id } => {
+            json!(manager.unsubscribe_resource_usage(id).await?)
+        }
+        Request::SetMonitor { id, settings } => {
+            json!(manager.set_monitor(id, settings, events.clone()).await?)
+        }
+        Request::SetTriggers { id, triggers } => {
+            json!(manager.set_triggers(id, triggers).await?)
+        }
+        Request::StartFlowControl { id } => json!(manager.start_flow_control(id).await?),
+        Request::AckOutput { id, bytes } => json!(manager.ack_output(id, bytes).await?),
+        Request::SetVisibility { id, visible } => {
+            json!(manager.set_visibility(id, visible).await?)
+        }
+        Request::StartRecording {
+            id,
+            path,
+            record_input,
+        } => json!(manager.start_recording(id, &path, record_input).await?),
+        Request::StopRecording { id } => json!(manager.stop_recording(id).await?),
+        Request::StartTranscript { id, config } => {
+            json!(manager.start_transcript(id, &config).await?)
+        }
+        Request::StopTranscript { id } => json!(manager.stop_transcript(id).await?),
+        Request::CreateInputGroup { ids } => json!(manager.create_input_group(ids).await?),
+        Request::AddToGroup { group, id } => json!(manager.add_to_group(group, id).await?),
+        Request::RemoveFromGroup { group, id } => {
+            json!(manager.remove_from_group(group, id).await?)
+        }
+        Request::Replay { id } => json!(manager.replay(id).await?),
+        Request::AttachOutput { id } => {
+            let replay = |data| broadcaster.send_to(client_id, &DaemonMessage::Replay { id, data });
+            json!(manager.attach_with_replay(id, replay).await?)
+        }
+        Request::List => json!(manager.list().await?),
+    })
+}
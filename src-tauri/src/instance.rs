@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// What a launch asked for on the command line: `--cwd <dir>`,
+/// `--ssh <destination>` and/or `-e <command...>` (which takes the rest of
+/// the arguments).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LaunchRequest {
+    pub command: Option<Vec<String>>,
+    pub cwd: Option<String>,
+    pub ssh: Option<String>,
+}
+
+impl LaunchRequest {
+    pub fn from_args(args: &[String]) -> Self {
+        let mut request = Self::default();
+        let mut iter = args.iter().skip(1);
+
+        while let Some(arg) = iter.next() {
+            if arg == "-e" {
+                let command: Vec<String> = iter.by_ref().cloned().collect();
+                request.command = (!command.is_empty()).then_some(command);
+            } else if let Some(value) = flag_value(arg, "--cwd", &mut iter) {
+                request.cwd = Some(absolute(value));
+            } else if let Some(value) = flag_value(arg, "--ssh", &mut iter) {
+                request.ssh = Some(value);
+            }
+        }
+
+        request
+    }
+
+    /// Whether the launch asks for a specific tab, as opposed to just
+    /// starting the app.
+    pub fn is_empty(&self) -> bool {
+        self.command.is_none() && self.cwd.is_none() && self.ssh.is_none()
+    }
+}
+
+/// Accepts both `--flag value` and `--flag=value`.
+fn flag_value<'a>(
+    arg: &str,
+    flag: &str,
+    rest: &mut impl Iterator<Item = &'a String>,
+) -> Option<String> {
+    if arg == flag {
+        return rest.next().cloned();
+    }
+    arg.strip_prefix(flag)?
+        .strip_prefix('=')
+        .map(str::to_string)
+}
+
+/// Resolved against the launching process, which is not the instance that
+/// ends up opening the tab.
+pub fn absolute(path: String) -> String {
+    let relative = std::path::Path::new(&path);
+    if relative.is_absolute() || path.starts_with('~') {
+        return path;
+    }
+    std::env::current_dir()
+        .map(|dir| dir.join(relative).display().to_string())
+        .unwrap_or(path)
+}
+
+/// Launches forwarded by later invocations. Until the frontend has its
+/// `launch-request` listener in place they wait here, so one arriving
+/// while the window is still loading isn't lost.
+#[derive(Default)]
+pub struct LaunchQueue {
+    state: Mutex<QueueState>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    listening: bool,
+    pending: Vec<LaunchRequest>,
+}
+
+impl LaunchQueue {
+    /// Sends the launch to the frontend, or keeps it until it listens.
+    pub fn deliver(&self, app: &AppHandle, request: LaunchRequest) -> tauri::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.listening {
+            state.pending.push(request);
+            return Ok(());
+        }
+        // Under the lock, so it can't overtake launches still being taken.
+        app.emit("launch-request", request)
+    }
+
+    /// The frontend listens from now on; returns the launches that waited.
+    pub fn start_listening(&self) -> Vec<LaunchRequest> {
+        let mut state = self.state.lock().unwrap();
+        state.listening = true;
+        std::mem::take(&mut state.pending)
+    }
+}
+
+/// Brings the window forward after a tab was opened or focused from outside.
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{bind_socket, forward_to_running_instance, listen_for_launches};
+
+#[cfg(unix)]
+mod unix {
+    use super::LaunchRequest;
+    use crate::config::{abbyterm_dir, create_private_dir};
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use tauri::{AppHandle, Manager};
+
+    const FORWARD_TIMEOUT: Duration = Duration::from_secs(2);
+
+    fn socket_path() -> Option<PathBuf> {
+        abbyterm_dir().map(|dir| dir.join("instance.sock"))
+    }
+
+    /// Hands the request to an already running AbbyTerm. Returns false when
+    /// there is none, in which case this process should start normally.
+    pub fn forward_to_running_instance(request: &LaunchRequest) -> bool {
+        let Some(path) = socket_path() else {
+            return false;
+        };
+        let Ok(mut stream) = UnixStream::connect(&path) else {
+            return false;
+        };
+        let _ = stream.set_read_timeout(Some(FORWARD_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(FORWARD_TIMEOUT));
+
+        let Ok(mut line) = serde_json::to_string(request) else {
+            return false;
+        };
+        line.push('\n');
+        if stream.write_all(line.as_bytes()).is_err() {
+            return false;
+        }
+
+        // Wait for the acknowledgement so a wedged instance doesn't swallow
+        // the request.
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply).is_ok() && reply.trim() == "ok"
+    }
+
+    /// Listens for launches forwarded by later invocations and asks the
+    /// frontend to open them as tabs through the `LaunchQueue`.
+    pub fn listen_for_launches(app: AppHandle) {
+        let Some(path) = socket_path() else {
+            return;
+        };
+
+        let listener = match bind_socket(&path) {
+            Ok(listener) => listener,
+            Err(err) => {
+                eprintln!("Single-instance listener unavailable: {}", err);
+                return;
+            }
+        };
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(err) = handle_launch(&app, stream) {
+                    eprintln!("Ignoring forwarded launch: {}", err);
+                }
+            }
+        });
+    }
+
+    /// Binds a socket only the owner can connect to, replacing a stale one
+    /// left behind by a crash.
+    pub fn bind_socket(path: &Path) -> std::io::Result<UnixListener> {
+        if path.exists() {
+            // A live socket belongs to another instance that won the race.
+            if UnixStream::connect(path).is_ok() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AddrInUse,
+                    "another instance is already listening",
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        if let Some(parent) = path.parent() {
+            create_private_dir(parent)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    fn handle_launch(app: &AppHandle, stream: UnixStream) -> anyhow::Result<()> {
+        stream.set_read_timeout(Some(FORWARD_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            // Another instance checking whether this socket is alive.
+            return Ok(());
+        }
+        let request: LaunchRequest = serde_json::from_str(&line)?;
+
+        app.state::<super::LaunchQueue>().deliver(app, request)?;
+        super::show_main_window(app);
+
+        (&stream).write_all(b"ok\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn forward_to_running_instance(_request: &LaunchRequest) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+pub fn listen_for_launches(_app: AppHandle) {}
@@ -1,6 +1,10 @@
 mod commands;
+mod config;
+mod daemon;
+mod instance;
 mod pty;
 mod ratel_mode;
+mod remote;
 mod ssh_config;
 
 use commands::app_commands::*;
@@ -10,32 +14,68 @@ use commands::session_commands::*;
 use commands::shell_commands::*;
 use commands::ssh_commands::*;
 use commands::window_commands::*;
+use config::AppConfig;
+use instance::{LaunchQueue, LaunchRequest};
 use pty::manager::PtyManager;
+use pty::output::OutputChannels;
+use remote::RemoteControl;
 use std::sync::Mutex;
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 
+pub use daemon::run_daemon;
+pub use pty::bench::run_pty_bench;
 pub use ratel_mode::run_ratel;
+pub use remote::run_remote;
 
-struct InitialCliArgs {
-    args: Mutex<Option<Vec<String>>>,
+struct InitialLaunch {
+    request: Mutex<Option<LaunchRequest>>,
 }
 
+/// The tab requested on the command line that started this instance.
+/// Later launches arrive through the `launch-request` event instead.
 #[tauri::command]
-fn get_initial_args(state: State<'_, InitialCliArgs>) -> Option<Vec<String>> {
-    state.args.lock().unwrap().clone()
+fn get_launch_request(state: State<'_, InitialLaunch>) -> Option<LaunchRequest> {
+    state.request.lock().unwrap().clone()
+}
+
+/// Called once the frontend listens for `launch-request`. Returns the
+/// launches forwarded before then; later ones are emitted right away.
+#[tauri::command]
+fn take_queued_launches(queue: State<'_, LaunchQueue>) -> Vec<LaunchRequest> {
+    queue.start_listening()
+}
+
+/// Uses the session daemon when it is enabled, falling back to in-process
+/// sessions if it can't be reached.
+fn create_pty_manager(app: &AppHandle) -> PtyManager {
+    if !AppConfig::load().daemon.enabled {
+        return PtyManager::new();
+    }
+
+    #[cfg(unix)]
+    match tauri::async_runtime::block_on(daemon::DaemonClient::connect(app.clone())) {
+        Ok(client) => return PtyManager::with_daemon(client),
+        Err(err) => eprintln!(
+            "Session daemon unavailable, running sessions in-process: {}",
+            err
+        ),
+    }
+
+    #[cfg(not(unix))]
+    let _ = app;
+
+    PtyManager::new()
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let args: Vec<String> = std::env::args().collect();
-    let mut initial_args = None;
-
-    let e_index = args.iter().position(|arg| arg == "-e");
+    let launch = LaunchRequest::from_args(&args);
 
-    if let Some(index) = e_index {
-        if index + 1 < args.len() {
-            initial_args = Some(args[index + 1..].to_vec());
-        }
+    // `abbyterm -e ...`, `--cwd` or `--ssh` opens a tab in the instance
+    // that is already running instead of starting a second app.
+    if !launch.is_empty() && instance::forward_to_running_instance(&launch) {
+        return;
     }
 
     tauri::Builder::default()
@@ -43,12 +83,18 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_clipboard_manager::init())
-        .manage(PtyManager::new())
-        .manage(InitialCliArgs {
-            args: Mutex::new(initial_args),
+        .manage(InitialLaunch {
+            request: Mutex::new((!launch.is_empty()).then_some(launch)),
         })
-        .setup(|_app| {
-            // linux
+        .manage(LaunchQueue::default())
+        .manage(RemoteControl::default())
+        .manage(OutputChannels::default())
+        .setup(|app| {
+            app.manage(create_pty_manager(app.handle()));
+            instance::listen_for_launches(app.handle().clone());
+            if AppConfig::load().remote_control.enabled {
+                remote::listen_for_commands(app.handle().clone());
+            }
 
             Ok(())
         })
@@ -65,11 +111,34 @@ pub fn run() {
             create_ratel_session,
             pty_write,
             pty_write_bytes,
+            create_input_group,
+            add_to_group,
+            remove_from_group,
             pty_resize,
             pty_kill,
+            pty_signal,
+            pty_replay,
+            attach_pty_output,
+            pty_ack_output,
+            set_session_visibility,
+            list_pty_sessions,
+            start_recording,
+            stop_recording,
+            start_transcript,
+            stop_transcript,
+            create_playback_session,
+            playback_set_paused,
+            playback_seek,
+            playback_set_speed,
             prepare_zmodem_upload_files,
             read_zmodem_upload_chunk,
             get_session_cwd,
+            get_session_foreground,
+            get_session_resource_usage,
+            subscribe_session_resource_usage,
+            unsubscribe_session_resource_usage,
+            set_session_monitor,
+            set_session_triggers,
             // SSH commands
             get_ssh_hosts,
             // Shell commands
@@ -90,7 +159,8 @@ pub fn run() {
             get_session_snapshots,
             delete_session_snapshot,
             // CLI args
-            get_initial_args,
+            get_launch_request,
+            take_queued_launches,
             // App commands
             get_build_date,
             get_build_date_short,
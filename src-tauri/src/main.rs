@@ -14,5 +14,35 @@ fn main() {
         return;
     }
 
+    // Session daemon: owns the PTYs so shells survive the window closing.
+    // Started on demand by the app when `[daemon] enabled = true`.
+    if args.len() >= 2 && args[1] == "--daemon" {
+        if let Err(e) = abbyterm::run_daemon() {
+            eprintln!("session daemon error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Remote control: `abbyterm @ <command>` drives the running app over
+    // the socket in `$ABBYTERM_LISTEN_ON`. Needs `[remote_control] enabled`.
+    if args.len() >= 2 && args[1] == "@" {
+        if let Err(e) = abbyterm::run_remote(&args[2..]) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // PTY benchmark: compares per-session reader threads with the async
+    // reader sessions use, for throughput, threads and memory.
+    if args.len() >= 2 && args[1] == "--bench-pty" {
+        if let Err(e) = abbyterm::run_pty_bench(&args[2..]) {
+            eprintln!("pty benchmark error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     abbyterm::run();
 }
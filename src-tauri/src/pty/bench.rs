@@ -0,0 +1,227 @@
+use super::exit::wait_for_exit;
+use super::pty_io::PtyIo;
+use anyhow::{anyhow, Result};
+use portable_pty::{native_pty_system, Child, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use std::future::Future;
+use std::io::Read;
+use std::pin::Pin;
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+type Pty = (Box<dyn MasterPty + Send>, Box<dyn Child + Send + Sync>);
+type Reader = Pin<Box<dyn Future<Output = ()>>>;
+
+struct Session {
+    /// Kept open until the end; closing it hangs up the child.
+    _master: Box<dyn MasterPty + Send>,
+    killer: Box<dyn ChildKiller + Send + Sync>,
+    reader: Reader,
+}
+
+/// How sessions are read: a blocking thread per PTY plus one waiting for
+/// the child, as sessions used to be, or tasks on the tokio runtime.
+#[derive(Clone, Copy)]
+enum Mode {
+    Threads,
+    Async,
+}
+
+impl Mode {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Threads => "threads",
+            Self::Async => "async",
+        }
+    }
+}
+
+struct Workload {
+    /// Sessions sitting at `cat`, for the cost of an open tab.
+    idle: usize,
+    /// Sessions printing `bytes` each at the same time, for throughput.
+    busy: usize,
+    bytes: u64,
+}
+
+/// Entry point of `abbyterm --bench-pty [threads|async] [--idle N]
+/// [--busy N] [--megabytes N]`. Without a mode, runs both in fresh
+/// processes so their memory and thread counts don't mix, and prints one
+/// row each. Memory and threads are read from `/proc`, so they are only
+/// reported on Linux.
+pub fn run_pty_bench(args: &[String]) -> Result<()> {
+    let (mode, workload) = parse(args)?;
+    if let Some(mode) = mode {
+        return measure(mode, &workload);
+    }
+
+    println!(
+        "{} idle sessions, {} sessions printing {} MB each",
+        workload.idle,
+        workload.busy,
+        workload.bytes / 1_000_000
+    );
+    println!(
+        "{:<8} {:>14} {:>14} {:>12} {:>14}",
+        "mode", "threads/tab", "KB/idle tab", "MB/s", "peak RSS MB"
+    );
+    let exe = std::env::current_exe()?;
+    for mode in [Mode::Threads, Mode::Async] {
+        let status = Command::new(&exe)
+            .arg("--bench-pty")
+            .arg(mode.name())
+            .args(args)
+            .status()?;
+        if !status.success() {
+            return Err(anyhow!("{} benchmark failed", mode.name()));
+        }
+    }
+    Ok(())
+}
+
+fn parse(args: &[String]) -> Result<(Option<Mode>, Workload)> {
+    let mut mode = None;
+    let mut workload = Workload {
+        idle: 200,
+        busy: 50,
+        bytes: 20_000_000,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || -> Result<u64> {
+            args.next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| anyhow!("{} needs a number", arg))
+        };
+        match arg.as_str() {
+            "threads" => mode = Some(Mode::Threads),
+            "async" => mode = Some(Mode::Async),
+            "--idle" => workload.idle = value()? as usize,
+            "--busy" => workload.busy = value()? as usize,
+            "--megabytes" => workload.bytes = value()? * 1_000_000,
+            _ => return Err(anyhow!("Unknown argument: {}", arg)),
+        }
+    }
+    Ok((mode, workload))
+}
+
+fn measure(mode: Mode, workload: &Workload) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let _guard = runtime.enter();
+    let rss_before = process_status("VmRSS");
+    let threads_before = process_status("Threads");
+
+    let mut sessions = Vec::new();
+    for _ in 0..workload.idle {
+        sessions.push(read(mode, open(&["cat"])?, Arc::default())?);
+    }
+    std::thread::sleep(Duration::from_millis(500));
+    let rss_idle = process_status("VmRSS");
+    let threads_idle = process_status("Threads");
+
+    let received = Arc::new(AtomicU64::new(0));
+    let size = workload.bytes.to_string();
+    let started = Instant::now();
+    let mut busy = Vec::new();
+    for _ in 0..workload.busy {
+        let pty = open(&["head", "-c", &size, "/dev/zero"])?;
+        busy.push(read(mode, pty, received.clone())?);
+    }
+    for session in &mut busy {
+        runtime.block_on(&mut session.reader);
+    }
+    let elapsed = started.elapsed();
+    let rss_peak = process_status("VmHWM");
+
+    let per_tab = |after: Option<u64>, before: Option<u64>| {
+        after.zip(before).map(|(after, before)| {
+            after.saturating_sub(before) as f64 / workload.idle.max(1) as f64
+        })
+    };
+    let received = received.load(Ordering::Relaxed);
+    println!(
+        "{:<8} {:>14} {:>14} {:>12.0} {:>14}",
+        mode.name(),
+        show(per_tab(threads_idle, threads_before), 1),
+        show(per_tab(rss_idle, rss_before), 0),
+        received as f64 / 1e6 / elapsed.as_secs_f64(),
+        show(rss_peak.map(|kb| kb as f64 / 1024.0), 1)
+    );
+
+    for mut session in sessions.into_iter().chain(busy) {
+        let _ = session.killer.kill();
+    }
+    Ok(())
+}
+
+fn open(argv: &[&str]) -> Result<Pty> {
+    let pair = native_pty_system().openpty(PtySize {
+        rows: 24,
+        cols: 80,
+        pixel_width: 0,
+        pixel_height: 0,
+    })?;
+    let mut command = CommandBuilder::new(argv[0]);
+    command.args(&argv[1..]);
+    let child = pair.slave.spawn_command(command)?;
+    Ok((pair.master, child))
+}
+
+/// Reads the PTY until EOF, counting the bytes, and reaps its child.
+/// Returns the child's killer and a future that finishes with the reader.
+fn read(mode: Mode, (master, mut child): Pty, received: Arc<AtomicU64>) -> Result<Session> {
+    let killer = child.clone_killer();
+    let mut buf = vec![0u8; 8192];
+    let reader: Reader = match mode {
+        Mode::Threads => {
+            let mut reader = master.try_clone_reader()?;
+            let reader = std::thread::spawn(move || {
+                while let Ok(n @ 1..) = reader.read(&mut buf) {
+                    received.fetch_add(n as u64, Ordering::Relaxed);
+                }
+            });
+            std::thread::spawn(move || child.wait());
+            Box::pin(async move {
+                let _ = tokio::task::spawn_blocking(move || reader.join()).await;
+            })
+        }
+        Mode::Async => {
+            let io = PtyIo::new(master.as_ref())?;
+            let reader = tokio::spawn(async move {
+                while let Ok(n @ 1..) = io.read(&mut buf).await {
+                    received.fetch_add(n as u64, Ordering::Relaxed);
+                }
+            });
+            tokio::spawn(wait_for_exit(child));
+            Box::pin(async move {
+                let _ = reader.await;
+            })
+        }
+    };
+    Ok(Session {
+        _master: master,
+        killer,
+        reader,
+    })
+}
+
+/// A number from `/proc/self/status`, like `VmRSS` in KB or `Threads`.
+fn process_status(name: &str) -> Option<u64> {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+fn show(value: Option<f64>, decimals: usize) -> String {
+    value
+        .map(|value| format!("{:.*}", decimals, value))
+        .unwrap_or_else(|| "-".to_string())
+}
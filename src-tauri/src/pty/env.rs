@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+
+/// Variables that only make sense for AbbyTerm itself, set by the AppImage
+/// runtime or for WebKit. Leaking them into shells confuses programs
+/// started from the terminal. Entries ending in `*` match by prefix.
+pub const DEFAULT_ENV_DENYLIST: &[&str] = &[
+    // AppImage runtime
+    "APPDIR",
+    "APPIMAGE",
+    "APPIMAGE_*",
+    "ARGV0",
+    "OWD",
+    // WebKitGTK switches
+    "WEBKIT_*",
+];
+
+/// Dropped only when running from an AppImage, whose launcher sets them for
+/// the bundled GTK. Anywhere else the user set them.
+const APPIMAGE_ENV_DENYLIST: &[&str] = &["GDK_BACKEND"];
+
+/// Search paths that the AppImage runtime and bundling hooks point into
+/// the bundle: the dynamic loader, GTK/GDK/GIO modules, interpreters and
+/// plugins. Instead of dropping them, entries under `$APPDIR` are filtered
+/// out, so values the user set are kept.
+const APPDIR_PATH_VARS: &[&str] = &[
+    "PATH",
+    "XDG_DATA_DIRS",
+    "XDG_CONFIG_DIRS",
+    // Dynamic loader
+    "LD_LIBRARY_PATH",
+    "LD_PRELOAD",
+    // GTK / GDK / GIO modules
+    "GDK_PIXBUF_MODULE_FILE",
+    "GDK_PIXBUF_MODULEDIR",
+    "GIO_EXTRA_MODULES",
+    "GIO_MODULE_DIR",
+    "GSETTINGS_SCHEMA_DIR",
+    "GTK_DATA_PREFIX",
+    "GTK_EXE_PREFIX",
+    "GTK_IM_MODULE_FILE",
+    "GTK_PATH",
+    // Interpreters and plugins
+    "PERLLIB",
+    "PYTHONHOME",
+    "PYTHONPATH",
+    "QT_PLUGIN_PATH",
+];
+
+/// `[environment]` section of `~/.abbyterm/config.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvironmentConfig {
+    /// Build shell environments from a sanitized copy of AbbyTerm's own
+    /// environment. When false the parent environment is inherited as is.
+    pub scrub: bool,
+    /// Extra variables (or `PREFIX_*` patterns) to drop, on top of
+    /// [`DEFAULT_ENV_DENYLIST`].
+    pub deny: Vec<String>,
+    /// Variables (or patterns) that are always passed through, even when
+    /// they match the denylist.
+    pub allow: Vec<String>,
+}
+
+impl Default for EnvironmentConfig {
+    fn default() -> Self {
+        Self {
+            scrub: true,
+            deny: Vec::new(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+impl EnvironmentConfig {
+    fn is_denied(&self, key: &str, in_appimage: bool) -> bool {
+        if self.is_allowed(key) {
+            return false;
+        }
+
+        let appimage: &[&str] = if in_appimage {
+            APPIMAGE_ENV_DENYLIST
+        } else {
+            &[]
+        };
+        DEFAULT_ENV_DENYLIST
+            .iter()
+            .chain(appimage)
+            .copied()
+            .chain(self.deny.iter().map(String::as_str))
+            .any(|pattern| matches_pattern(pattern, key))
+    }
+
+    fn is_allowed(&self, key: &str) -> bool {
+        self.allow
+            .iter()
+            .any(|pattern| matches_pattern(pattern, key))
+    }
+}
+
+/// Returns AbbyTerm's environment with launcher-injected variables removed.
+pub fn sanitized_env(config: &EnvironmentConfig) -> Vec<(OsString, OsString)> {
+    let vars: Vec<(OsString, OsString)> = std::env::vars_os().collect();
+    if !config.scrub {
+        return vars;
+    }
+
+    let appdir = std::env::var_os("APPDIR").filter(|dir| !dir.is_empty());
+    let in_appimage = appdir.is_some() || std::env::var_os("APPIMAGE").is_some();
+
+    vars.into_iter()
+        .filter_map(|(key, value)| {
+            let Some(name) = key.to_str() else {
+                return Some((key, value));
+            };
+
+            if config.is_denied(name, in_appimage) {
+                return None;
+            }
+
+            match &appdir {
+                Some(appdir) if APPDIR_PATH_VARS.contains(&name) && !config.is_allowed(name) => {
+                    let cleaned = strip_appdir_entries(&value, appdir)?;
+                    Some((key, cleaned))
+                }
+                _ => Some((key, value)),
+            }
+        })
+        .collect()
+}
+
+fn matches_pattern(pattern: &str, key: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => key.starts_with(prefix),
+        None => pattern == key,
+    }
+}
+
+/// Removes `$APPDIR/...` entries from a colon-separated search path. Returns
+/// `None` when nothing is left.
+fn strip_appdir_entries(value: &OsString, appdir: &OsString) -> Option<OsString> {
+    let appdir = std::path::Path::new(appdir);
+    let kept: Vec<_> = std::env::split_paths(value)
+        .filter(|entry| !entry.starts_with(appdir))
+        .collect();
+
+    if kept.is_empty() {
+        return None;
+    }
+    std::env::join_paths(kept).ok()
+}
@@ -0,0 +1,45 @@
+use super::output::OutputChannels;
+use crate::daemon::Broadcaster;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+/// Where a session's events go: straight to the webview, or, inside the
+/// session daemon, to every connected window.
+#[derive(Clone)]
+pub enum EventSink {
+    App(AppHandle),
+    Daemon(Broadcaster),
+}
+
+impl EventSink {
+    pub fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        match self {
+            Self::App(app) => {
+                let _ = app.emit(event, payload);
+            }
+            Self::Daemon(broadcaster) => broadcaster.emit(event, &payload),
+        }
+    }
+
+    /// Session output: to the terminal's channel, or to the windows, which
+    /// feed it into the same channels. Returns whether anyone may have
+    /// received it.
+    pub fn output(&self, id: Uuid, data: &[u8]) -> bool {
+        match self {
+            Self::App(app) => app
+                .try_state::<OutputChannels>()
+                .is_some_and(|channels| channels.send(id, data)),
+            Self::Daemon(broadcaster) => {
+                broadcaster.output(id, data);
+                broadcaster.client_count() > 0
+            }
+        }
+    }
+}
+
+impl From<AppHandle> for EventSink {
+    fn from(app: AppHandle) -> Self {
+        Self::App(app)
+    }
+}
@@ -0,0 +1,120 @@
+use portable_pty::Child;
+use serde::Serialize;
+
+/// How a session's child process ended, sent as the `pty-exit-{id}` payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitInfo {
+    pub success: bool,
+    /// Exit code when the process exited normally.
+    pub exit_code: Option<i32>,
+    /// Signal number when the process was terminated by a signal.
+    pub signal: Option<i32>,
+    pub signal_name: Option<String>,
+    pub core_dumped: bool,
+}
+
+impl ExitInfo {
+    fn unknown() -> Self {
+        Self {
+            success: false,
+            exit_code: None,
+            signal: None,
+            signal_name: None,
+            core_dumped: false,
+        }
+    }
+
+    fn from_portable(status: portable_pty::ExitStatus) -> Self {
+        Self {
+            success: status.success(),
+            exit_code: Some(status.exit_code() as i32),
+            signal: None,
+            signal_name: None,
+            core_dumped: false,
+        }
+    }
+
+    #[cfg(unix)]
+    fn from_wait_status(status: libc::c_int) -> Self {
+        if libc::WIFEXITED(status) {
+            let code = libc::WEXITSTATUS(status);
+            return Self {
+                success: code == 0,
+                exit_code: Some(code),
+                ..Self::unknown()
+            };
+        }
+
+        if libc::WIFSIGNALED(status) {
+            let signal = libc::WTERMSIG(status);
+            return Self {
+                signal: Some(signal),
+                signal_name: Some(super::signal::signal_name(signal)),
+                core_dumped: libc::WCOREDUMP(status),
+                ..Self::unknown()
+            };
+        }
+
+        Self::unknown()
+    }
+}
+
+/// Waits until `child` terminates and reaps it.
+///
+/// On Unix we call `waitpid` ourselves because `portable_pty::ExitStatus`
+/// only exposes a description of the terminating signal, not its number.
+/// Every SIGCHLD wakes the waiter to check on its child, so waiting ties
+/// up no thread.
+pub async fn wait_for_exit(mut child: Box<dyn Child + Send + Sync>) -> ExitInfo {
+    #[cfg(unix)]
+    if let Some(pid) = child.process_id() {
+        return wait_for_pid(pid as libc::pid_t).await;
+    }
+
+    let waited = tokio::task::spawn_blocking(move || child.wait()).await;
+    match waited {
+        Ok(Ok(status)) => ExitInfo::from_portable(status),
+        Ok(Err(err)) => {
+            eprintln!("Failed to wait for PTY child: {}", err);
+            ExitInfo::unknown()
+        }
+        Err(err) => {
+            eprintln!("Failed to wait for PTY child: {}", err);
+            ExitInfo::unknown()
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_pid(pid: libc::pid_t) -> ExitInfo {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    // Listening before the first check, so an exit in between still
+    // wakes us. Without the signal, fall back to polling.
+    let mut sigchld = signal(SignalKind::child())
+        .map_err(|err| eprintln!("Polling for PTY child {}: {}", pid, err))
+        .ok();
+
+    let mut status: libc::c_int = 0;
+    loop {
+        let result = unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) };
+        if result == pid {
+            return ExitInfo::from_wait_status(status);
+        }
+        if result == 0 {
+            match sigchld.as_mut() {
+                Some(sigchld) => {
+                    sigchld.recv().await;
+                }
+                None => tokio::time::sleep(std::time::Duration::from_millis(100)).await,
+            }
+            continue;
+        }
+
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            eprintln!("Failed to wait for PTY child {}: {}", pid, err);
+            return ExitInfo::unknown();
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Output is sent at most this often while it keeps coming; the first chunk
+/// after a quiet spell goes out right away, so echo stays immediate.
+pub const FLUSH_INTERVAL: Duration = Duration::from_millis(4);
+
+/// A batch this large is sent without waiting for `FLUSH_INTERVAL`.
+pub const FLUSH_BYTES: usize = 64 * 1024;
+
+/// With this much output sent but not yet acknowledged by the terminal,
+/// the session stops reading from the PTY, and the kernel blocks the child
+/// once the PTY buffer is full.
+pub const HIGH_WATERMARK: usize = 1024 * 1024;
+
+/// Reading resumes once the unacknowledged output drops below this. The
+/// terminal acknowledges in batches smaller than this, so reading always
+/// resumes once it has caught up.
+pub const LOW_WATERMARK: usize = 256 * 1024;
+
+/// Output of a hidden session is held back, and once there is twice this
+/// much, only the last lines up to this size are kept.
+pub const HIDDEN_TAIL_BYTES: usize = 512 * 1024;
+
+const ESC: u8 = 0x1b;
+
+#[derive(Default)]
+struct FlowState {
+    pending: Vec<u8>,
+    last_flush: Option<Instant>,
+    /// A terminal acknowledges what it has rendered. Without one, output is
+    /// never held back.
+    acking: bool,
+    unacked: usize,
+    paused: bool,
+    /// The session's tab isn't shown; output waits for it to be.
+    hidden: bool,
+    /// The output can't be cut short, so it isn't held back even when
+    /// hidden.
+    keep_whole: bool,
+    closed: bool,
+}
+
+enum Due {
+    Now,
+    At(Instant),
+    Later,
+    Done,
+}
+
+impl FlowState {
+    fn holding(&self) -> bool {
+        self.hidden && !self.keep_whole
+    }
+
+    fn has_room(&self) -> bool {
+        self.closed || self.holding() || (!self.paused && self.pending.len() < FLUSH_BYTES)
+    }
+
+    fn due(&self) -> Due {
+        if self.pending.is_empty() {
+            return if self.closed { Due::Done } else { Due::Later };
+        }
+        if self.closed {
+            return Due::Now;
+        }
+        if self.holding() {
+            return Due::Later;
+        }
+        match self.last_flush.map(|last| last + FLUSH_INTERVAL) {
+            Some(due) if due > Instant::now() && self.pending.len() < FLUSH_BYTES => Due::At(due),
+            _ => Due::Now,
+        }
+    }
+
+    /// Drops all but the tail of the held-back output. The tail starts
+    /// after a newline, failing that after a carriage return or at an
+    /// escape sequence, so no sequence or character is cut in half. With
+    /// none of those in it, nothing is dropped.
+    fn compact(&mut self) {
+        if self.pending.len() < HIDDEN_TAIL_BYTES * 2 {
+            return;
+        }
+        let cut = self.pending.len() - HIDDEN_TAIL_BYTES;
+        let tail = &self.pending[cut..];
+        let after = |byte: u8| tail.iter().position(|&b| b == byte).map(|at| at + 1);
+        let start = after(b'\n')
+            .or_else(|| after(b'\r'))
+            .or_else(|| tail.iter().position(|&b| b == ESC));
+        if let Some(start) = start {
+            self.pending.drain(..cut + start);
+        }
+    }
+}
+
+/// Paces a session's output between its reader task and its flusher
+/// task: reads are coalesced into batches, and reading pauses while the
+/// terminal is too far behind (the watermark scheme recommended by
+/// xterm.js).
+#[derive(Default)]
+pub struct OutputFlow {
+    state: Mutex<FlowState>,
+    changed: Notify,
+}
+
+impl OutputFlow {
+    /// Waits until the reader task may queue more output: while reading
+    /// is paused, or while a full batch waits for the flusher, unless the
+    /// session is hidden.
+    pub async fn wait_for_room(&self) {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            // Registered before looking, so a change in between isn't missed.
+            changed.as_mut().enable();
+            match self.state.lock() {
+                Ok(state) if !state.has_room() => {}
+                _ => return,
+            }
+            changed.await;
+        }
+    }
+
+    /// Queues output from the reader task. A hidden session keeps only
+    /// the tail of what it queues.
+    pub fn queue(&self, data: &[u8]) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.pending.extend_from_slice(data);
+        if state.holding() {
+            state.compact();
+            return;
+        }
+        if state.acking && state.unacked + state.pending.len() >= HIGH_WATERMARK {
+            state.paused = true;
+        }
+        drop(state);
+        self.changed.notify_waiters();
+    }
+
+    /// Waits until a batch is due for the flusher task. False once the
+    /// reader has closed the flow and everything was handed out. Nothing
+    /// is due while the session is hidden, until it ends.
+    pub async fn batch_due(&self) -> bool {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            let due = match self.state.lock() {
+                Ok(state) => state.due(),
+                Err(_) => return false,
+            };
+            match due {
+                Due::Now => return true,
+                Due::Done => return false,
+                Due::At(due) => {
+                    let _ = tokio::time::timeout_at(due.into(), changed).await;
+                }
+                Due::Later => changed.await,
+            }
+        }
+    }
+
+    /// Takes the pending output if it is due. The flusher calls this under
+    /// the scrollback lock, so a replay never overlaps what it sends.
+    pub fn take_batch(&self) -> Option<Vec<u8>> {
+        let mut state = self.state.lock().ok()?;
+        if !matches!(state.due(), Due::Now) {
+            return None;
+        }
+        let batch = std::mem::take(&mut state.pending);
+        state.last_flush = Some(Instant::now());
+        if state.acking {
+            state.unacked += batch.len();
+        }
+        drop(state);
+        // The reader may be waiting for room.
+        self.changed.notify_waiters();
+        Some(batch)
+    }
+
+    /// Output queued but not sent yet: the tail of the scrollback that
+    /// the next batch carries.
+    pub fn pending_len(&self) -> usize {
+        self.state.lock().map_or(0, |state| state.pending.len())
+    }
+
+    /// A terminal that acknowledges output started consuming it. Output
+    /// sent to an earlier terminal no longer counts.
+    pub fn start_acking(&self) {
+        self.update(|state| {
+            state.acking = true;
+            state.unacked = 0;
+        });
+    }
+
+    /// Nothing acknowledges output any more, e.g. the terminal went away.
+    pub fn stop_acking(&self) {
+        self.update(|state| {
+            state.acking = false;
+            state.unacked = 0;
+        });
+    }
+
+    pub fn ack(&self, bytes: usize) {
+        self.update(|state| state.unacked = state.unacked.saturating_sub(bytes));
+    }
+
+    /// Whether the session's tab is shown. Output held back while it was
+    /// hidden goes out as one batch once it is.
+    pub fn set_visible(&self, visible: bool) {
+        self.update(|state| state.hidden = !visible);
+    }
+
+    /// Set while a full-screen program or a ZMODEM transfer is running:
+    /// trimming the output would break the screen or the transfer, so a
+    /// hidden session keeps sending it.
+    pub fn set_keep_whole(&self, keep_whole: bool) {
+        self.update(|state| state.keep_whole = keep_whole);
+    }
+
+    /// The reader hit EOF: hand out what is left and wake everyone up.
+    pub fn close(&self) {
+        self.update(|state| state.closed = true);
+    }
+
+    fn update(&self, f: impl FnOnce(&mut FlowState)) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        f(&mut state);
+        if state.paused
+            && (state.holding()
+                || !state.acking
+                || state.unacked + state.pending.len() < LOW_WATERMARK)
+        {
+            state.paused = false;
+        }
+        drop(state);
+        self.changed.notify_waiters();
+    }
+}
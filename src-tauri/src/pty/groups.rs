@@ -0,0 +1,103 @@
+use anyhow::Result;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Sessions whose input is mirrored to each other ("synchronized panes").
+/// A session belongs to at most one group, and a group disappears with its
+/// last member.
+#[derive(Default)]
+pub struct InputGroups {
+    members: HashMap<Uuid, Vec<Uuid>>,
+    group_of: HashMap<Uuid, Uuid>,
+}
+
+impl InputGroups {
+    pub fn create(&mut self, sessions: &[Uuid]) -> Uuid {
+        let group = Uuid::new_v4();
+        self.members.insert(group, Vec::new());
+        for &session in sessions {
+            self.join(group, session);
+        }
+        group
+    }
+
+    /// Adds the session to `group`, moving it out of any other group.
+    pub fn add(&mut self, group: Uuid, session: Uuid) -> Result<()> {
+        if !self.members.contains_key(&group) {
+            anyhow::bail!("Input group not found");
+        }
+        self.join(group, session);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, group: Uuid, session: Uuid) -> Result<()> {
+        if self.group_of.get(&session) != Some(&group) {
+            anyhow::bail!("Session is not in this input group");
+        }
+        self.leave(session);
+        Ok(())
+    }
+
+    /// Takes the session out of its group, if it has one.
+    pub fn leave(&mut self, session: Uuid) {
+        let Some(group) = self.group_of.remove(&session) else {
+            return;
+        };
+        if let Some(members) = self.members.get_mut(&group) {
+            members.retain(|&member| member != session);
+            if members.is_empty() {
+                self.members.remove(&group);
+            }
+        }
+    }
+
+    /// Every session that input typed into `session` goes to, or `None`
+    /// when it isn't grouped.
+    pub fn members_of(&self, session: Uuid) -> Option<Vec<Uuid>> {
+        let group = self.group_of.get(&session)?;
+        self.members.get(group).cloned()
+    }
+
+    fn join(&mut self, group: Uuid, session: Uuid) {
+        if self.group_of.get(&session) == Some(&group) {
+            return;
+        }
+        self.leave(session);
+        self.group_of.insert(session, group);
+        if let Some(members) = self.members.get_mut(&group) {
+            members.push(session);
+        }
+    }
+}
+
+/// The frontend wraps a paste in bracketed paste markers when the program
+/// in the pane it was typed into asked for them. Members running a program
+/// that didn't would see the markers as literal input, so they get the
+/// plain text instead.
+pub fn paste_for_member(data: &[u8], bracketed_paste: bool) -> Cow<'_, [u8]> {
+    if bracketed_paste || !(contains(data, PASTE_START) || contains(data, PASTE_END)) {
+        return Cow::Borrowed(data);
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    let mut rest = data;
+    while !rest.is_empty() {
+        if rest.starts_with(PASTE_START) {
+            rest = &rest[PASTE_START.len()..];
+        } else if rest.starts_with(PASTE_END) {
+            rest = &rest[PASTE_END.len()..];
+        } else {
+            out.push(rest[0]);
+            rest = &rest[1..];
+        }
+    }
+    Cow::Owned(out)
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+    data.windows(needle.len()).any(|window| window == needle)
+}
@@ -0,0 +1,98 @@
+use super::pty_io::PtyIo;
+use super::session::SessionShared;
+use anyhow::Result;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::watch;
+
+/// Writes a session may have queued before callers wait for room. A paste
+/// is a single write, however large.
+pub const INPUT_QUEUE_CAPACITY: usize = 256;
+
+/// The sending end of a session's input. Writes are queued for the
+/// session's writer task, so a child that stops reading its input only
+/// holds up writes to its own session.
+#[derive(Clone)]
+pub struct InputQueue {
+    sender: mpsc::Sender<Vec<u8>>,
+    /// Set once the session can't take input any more.
+    closed: Arc<watch::Sender<bool>>,
+    shared: Arc<SessionShared>,
+}
+
+impl InputQueue {
+    /// Starts the writer task that feeds queued input to the PTY. It ends
+    /// once the queue is closed or every `InputQueue` is dropped, or when
+    /// the PTY stops taking input.
+    pub fn start(io: Arc<PtyIo>, shared: Arc<SessionShared>) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<Vec<u8>>(INPUT_QUEUE_CAPACITY);
+        let closed = Arc::new(watch::Sender::new(false));
+
+        let mut close = closed.subscribe();
+        let counter = shared.clone();
+        tokio::spawn(async move {
+            let writing = async {
+                while let Some(data) = receiver.recv().await {
+                    if let Err(err) = io.write_all(&data).await {
+                        eprintln!("Error writing PTY input: {}", err);
+                        break;
+                    }
+                    counter
+                        .bytes_in
+                        .fetch_add(data.len() as u64, Ordering::Relaxed);
+                }
+            };
+            // A write to a child that never reads again waits forever.
+            tokio::select! {
+                _ = writing => {}
+                _ = close.wait_for(|closed| *closed) => {}
+            }
+        });
+
+        Self {
+            sender,
+            closed,
+            shared,
+        }
+    }
+
+    /// Queues `data`, waiting while the queue is full.
+    pub async fn send(&self, data: &[u8]) -> Result<()> {
+        let mut closed = self.closed.subscribe();
+        tokio::select! {
+            sent = self.sender.send(data.to_vec()) => {
+                sent.map_err(|_| anyhow::anyhow!("Session no longer takes input"))?;
+            }
+            _ = closed.wait_for(|closed| *closed) => {
+                return Err(anyhow::anyhow!("Session no longer takes input"));
+            }
+        }
+        self.record(data);
+        Ok(())
+    }
+
+    /// Queues `data` unless the queue is full, for writers that must not
+    /// wait on the session, like input mirrored to a group.
+    pub fn try_send(&self, data: &[u8]) -> Result<()> {
+        match self.sender.try_send(data.to_vec()) {
+            Ok(()) => {
+                self.record(data);
+                Ok(())
+            }
+            Err(TrySendError::Full(_)) => Err(anyhow::anyhow!("Session is not reading its input")),
+            Err(TrySendError::Closed(_)) => Err(anyhow::anyhow!("Session no longer takes input")),
+        }
+    }
+
+    /// Stops the writer and fails writes waiting for room once the
+    /// session has ended. Input the child never read is dropped; a PTY
+    /// master doesn't fail writes after the slave side is closed.
+    pub fn close(&self) {
+        self.closed.send_replace(true);
+    }
+
+    fn record(&self, data: &[u8]) {
+        self.shared.with_recorder(|recorder| recorder.input(data));
+    }
+}
@@ -1,92 +1,844 @@
-use super::session::PtySession;
+use super::events::EventSink;
+use super::exit::ExitInfo;
+use super::groups::{paste_for_member, InputGroups};
+use super::input::InputQueue;
+use super::modes::ModeScanner;
+use super::monitor::MonitorSettings;
+use super::osc::{OscEvent, OscScanner};
+use super::output::{send_raw, OutputChannels};
+use super::playback::Playback;
+use super::process::{get_process_cwd, inspect_process, ProcessInfo};
+use super::pty_io::PtyIo;
+use super::recording::Recorder;
+use super::session::{PtySession, ReportedCwd, SessionShared, RESIZE_INTERVAL};
+use super::signal::SignalTarget;
+use super::transcript::{LoggingConfig, TranscriptLogger};
+use super::triggers::{TriggerHit, TriggerMatcher, TriggerRule};
+use super::unix_pty::{CellSize, SpawnOptions};
+use super::usage::{measure, ResourceUsage, TreeSample, UsageSubscriptions};
+use super::zmodem::ZmodemDetector;
+use crate::daemon::protocol::Request;
+use crate::daemon::DaemonClient;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::io::Read;
-use std::path::PathBuf;
+use std::path::Path;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::ipc::Channel;
+use tauri::AppHandle;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+#[derive(Serialize, Deserialize)]
+pub struct PtySessionInfo {
+    pub id: String,
+    /// Program and arguments the session was started with.
+    pub command: String,
+    pub args: Vec<String>,
+    pub pid: Option<u32>,
+    pub cols: u16,
+    pub rows: u16,
+    /// Milliseconds since the Unix epoch.
+    pub created_at_ms: u64,
+    /// Bytes written to the child and read from it.
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Whether the child is still running.
+    pub alive: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct CwdChangedEvent {
+    session_id: String,
+    cwd: String,
+    host: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct CommandStartedEvent {
+    session_id: String,
+}
+
+#[derive(Clone, Serialize)]
+struct CommandFinishedEvent {
+    session_id: String,
+    exit_code: Option<i32>,
+    duration_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct SessionActivityEvent {
+    session_id: String,
+    /// How long the session had been quiet before this output.
+    quiet_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct SessionSilenceEvent {
+    session_id: String,
+    idle_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct SessionTriggerEvent {
+    session_id: String,
+    pattern: String,
+    line: String,
+    matched: String,
+    /// The trigger's response was written to the session.
+    responded: bool,
+}
+
+/// Time between the two samples of a one-off resource usage reading.
+const USAGE_SAMPLE_WINDOW: Duration = Duration::from_millis(250);
+
 pub struct PtyManager {
-    sessions: Arc<Mutex<HashMap<Uuid, PtySession>>>,
+    /// Held only long enough to look a session up; each session does its
+    /// own locking.
+    sessions: Arc<Mutex<HashMap<Uuid, Arc<PtySession>>>>,
+    playbacks: Arc<Mutex<HashMap<Uuid, Playback>>>,
+    groups: Mutex<InputGroups>,
+    /// Sessions getting `session-resource-usage` events.
+    usage_subscriptions: UsageSubscriptions,
+    /// Tasks emitting `session-silence`, by session.
+    silence_watchers: Arc<Mutex<HashMap<Uuid, tokio::task::JoinHandle<()>>>>,
+    /// Set when sessions live in the session daemon; PTY operations are
+    /// then forwarded to it instead of using `sessions`.
+    daemon: Option<DaemonClient>,
 }
 
 impl PtyManager {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            playbacks: Arc::new(Mutex::new(HashMap::new())),
+            groups: Mutex::new(InputGroups::default()),
+            usage_subscriptions: UsageSubscriptions::new(),
+            silence_watchers: Arc::new(Mutex::new(HashMap::new())),
+            daemon: None,
         }
     }
 
-    pub async fn create_session(
-        &self,
-        shell: Option<String>,
-        args: Option<Vec<String>>,
-        cwd: Option<PathBuf>,
-        cols: u16,
-        rows: u16,
-        app: AppHandle,
-    ) -> Result<Uuid> {
-        let session = PtySession::new(shell, args, cwd, cols, rows)?;
+    pub fn with_daemon(daemon: DaemonClient) -> Self {
+        Self {
+            daemon: Some(daemon),
+            ..Self::new()
+        }
+    }
+
+    pub async fn create_session(&self, options: SpawnOptions, events: EventSink) -> Result<Uuid> {
+        if let Some(daemon) = &self.daemon {
+            let options = Box::new(options);
+            return daemon.call_as(Request::Create { options }).await;
+        }
+
+        let mut session = PtySession::new(options)?;
         let id = session.id;
 
-        let reader = session.try_clone_reader()?;
+        let io = session.io();
+        let shared = session.shared.clone();
+        let waiter = session.spawn_waiter()?;
 
-        self.sessions.lock().await.insert(id, session);
+        let input = session.input.clone();
+        self.sessions.lock().await.insert(id, Arc::new(session));
 
-        self.start_output_task(id, reader, app);
+        self.start_output_task(id, io, shared, input, waiter, events);
+
+        Ok(id)
+    }
 
+    /// Plays an asciicast recording as a read-only session whose output
+    /// reaches the terminal the same way as a live one's.
+    pub async fn create_playback(&self, path: &Path, speed: f64, app: AppHandle) -> Result<Uuid> {
+        let id = Uuid::new_v4();
+        let playback = Playback::start(id, path, speed, app)?;
+        self.playbacks.lock().await.insert(id, playback);
         Ok(id)
     }
 
+    pub async fn control_playback(&self, id: Uuid, f: impl FnOnce(&Playback)) -> Result<()> {
+        let playbacks = self.playbacks.lock().await;
+        let playback = playbacks
+            .get(&id)
+            .ok_or_else(|| anyhow::anyhow!("Playback session not found"))?;
+        f(playback);
+        Ok(())
+    }
+
     pub async fn write(&self, id: Uuid, data: &[u8]) -> Result<()> {
-        let mut sessions = self.sessions.lock().await;
-        if let Some(session) = sessions.get_mut(&id) {
-            session.write(data)?;
+        if self.playbacks.lock().await.contains_key(&id) {
+            return Err(anyhow::anyhow!("Playback sessions are read-only"));
+        }
+        if let Some(daemon) = &self.daemon {
+            let data = data.to_vec();
+            return daemon.call_as(Request::Write { id, data }).await;
+        }
+
+        let members = self.groups.lock().await.members_of(id);
+        let Some(members) = members else {
+            let session = self.sessions.lock().await.get(&id).cloned();
+            if let Some(session) = session {
+                session.input.send(data).await?;
+            }
+            return Ok(());
+        };
+
+        // Input typed into a grouped session goes to every member. A member
+        // that has ended or fails to take it is dropped from the group
+        // rather than stopping the broadcast; only a failure of the session
+        // typed into is reported. Only that session is waited on; the
+        // others must have room in their queues.
+        let members: Vec<(Uuid, Option<Arc<PtySession>>)> = {
+            let sessions = self.sessions.lock().await;
+            members
+                .into_iter()
+                .map(|member| (member, sessions.get(&member).cloned()))
+                .collect()
+        };
+        let mut result = Ok(());
+        let mut dead = Vec::new();
+        for (member, session) in members {
+            let Some(session) = session else {
+                dead.push(member);
+                continue;
+            };
+            if member == id {
+                if let Err(err) = session.input.send(data).await {
+                    result = Err(err);
+                }
+                continue;
+            }
+            if session.has_exited() {
+                dead.push(member);
+                continue;
+            }
+            let data = paste_for_member(data, session.shared.bracketed_paste());
+            if let Err(err) = session.input.try_send(&data) {
+                eprintln!("Dropping {} from its input group: {}", member, err);
+                dead.push(member);
+            }
+        }
+
+        if !dead.is_empty() {
+            let mut groups = self.groups.lock().await;
+            for member in dead {
+                groups.leave(member);
+            }
+        }
+        result
+    }
+
+    /// Groups sessions so that input written to any of them reaches all of
+    /// them. Sessions already in a group move to the new one.
+    pub async fn create_input_group(&self, ids: Vec<Uuid>) -> Result<Uuid> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::CreateInputGroup { ids }).await;
+        }
+
+        self.ensure_sessions(&ids).await?;
+        Ok(self.groups.lock().await.create(&ids))
+    }
+
+    pub async fn add_to_group(&self, group: Uuid, id: Uuid) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::AddToGroup { group, id }).await;
+        }
+
+        self.ensure_sessions(&[id]).await?;
+        self.groups.lock().await.add(group, id)
+    }
+
+    pub async fn remove_from_group(&self, group: Uuid, id: Uuid) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::RemoveFromGroup { group, id }).await;
+        }
+
+        self.groups.lock().await.remove(group, id)
+    }
+
+    async fn ensure_sessions(&self, ids: &[Uuid]) -> Result<()> {
+        let sessions = self.sessions.lock().await;
+        if ids.iter().all(|id| sessions.contains_key(id)) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Session not found"))
         }
-        Ok(())
     }
 
-    pub async fn resize(&self, id: Uuid, cols: u16, rows: u16) -> Result<()> {
-        let mut sessions = self.sessions.lock().await;
-        if let Some(session) = sessions.get_mut(&id) {
-            session.resize(cols, rows)?;
+    pub async fn resize(
+        &self,
+        id: Uuid,
+        cols: u16,
+        rows: u16,
+        cell: Option<CellSize>,
+    ) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            return daemon
+                .call_as(Request::Resize {
+                    id,
+                    cols,
+                    rows,
+                    cell,
+                })
+                .await;
+        }
+
+        let Some(session) = self.sessions.lock().await.get(&id).cloned() else {
+            return Ok(());
+        };
+        if session.resize(cols, rows, cell)? {
+            // Deliver the latest size once the burst has had time to settle.
+            tokio::spawn(async move {
+                tokio::time::sleep(RESIZE_INTERVAL).await;
+                let _ = session.flush_resize();
+            });
         }
         Ok(())
     }
 
-    pub async fn kill(&self, id: Uuid) -> Result<()> {
-        let mut sessions = self.sessions.lock().await;
-        if let Some(mut session) = sessions.remove(&id) {
-            let _ = session.kill();
+    /// Removes the session and terminates its child: SIGHUP first, SIGKILL
+    /// once `grace` has passed.
+    pub async fn kill(&self, id: Uuid, grace: Duration) -> Result<()> {
+        // Dropping a playback stops its player thread.
+        if self.playbacks.lock().await.remove(&id).is_some() {
+            return Ok(());
+        }
+        if let Some(daemon) = &self.daemon {
+            let grace_ms = grace.as_millis() as u64;
+            return daemon.call_as(Request::Kill { id, grace_ms }).await;
+        }
+
+        self.groups.lock().await.leave(id);
+        self.usage_subscriptions.unsubscribe(id);
+        if let Some(watcher) = self.silence_watchers.lock().await.remove(&id) {
+            watcher.abort();
+        }
+        let session = self.sessions.lock().await.remove(&id);
+        if let Some(session) = session {
+            // A reader waiting for acknowledgements would never see EOF.
+            session.shared.flow.stop_acking();
+            // Killing waits out the grace period; keep it off the runtime.
+            tokio::task::spawn_blocking(move || {
+                let _ = session.kill(grace);
+            });
         }
         Ok(())
     }
 
+    #[cfg(unix)]
+    pub async fn signal(&self, id: Uuid, signal: &str, target: SignalTarget) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            let signal = signal.to_string();
+            return daemon.call_as(Request::Signal { id, signal, target }).await;
+        }
+
+        let signal = super::signal::parse_signal(signal)?;
+        let session = self.session(id).await?;
+        session.signal(signal, target)
+    }
+
+    #[cfg(not(unix))]
+    pub async fn signal(&self, _id: Uuid, _signal: &str, _target: SignalTarget) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Sending signals is not supported on this platform"
+        ))
+    }
+
+    /// Prefers the directory reported by shell integration, which stays
+    /// meaningful inside ssh, over the local shell's `/proc` cwd.
     pub async fn get_cwd(&self, id: Uuid) -> Result<String> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::GetCwd { id }).await;
+        }
+
+        let session = self.session(id).await?;
+        // A report from another host, e.g. a shell inside ssh, says
+        // nothing about the directory here.
+        if let Some(cwd) = session.reported_cwd() {
+            return Ok(cwd);
+        }
+        let pid = session
+            .get_child_pid()
+            .ok_or_else(|| anyhow::anyhow!("Session PID unavailable"))?;
+        get_process_cwd(pid as i32)
+    }
+
+    pub async fn get_foreground(&self, id: Uuid) -> Result<ProcessInfo> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::GetForeground { id }).await;
+        }
+
+        let session = self.session(id).await?;
+        let pgid = session
+            .pty
+            .foreground_pgid()
+            .ok_or_else(|| anyhow::anyhow!("Foreground process group unavailable"))?;
+        inspect_process(pgid, session.get_child_pid())
+    }
+
+    /// CPU, memory and the busiest processes of the session's process tree,
+    /// measured over a short window.
+    pub async fn resource_usage(&self, id: Uuid) -> Result<ResourceUsage> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::GetResourceUsage { id }).await;
+        }
+
+        let pid = self.child_pid(id).await?;
+        tokio::task::spawn_blocking(move || measure(pid, USAGE_SAMPLE_WINDOW, id.to_string()))
+            .await?
+    }
+
+    /// Emits `session-resource-usage` for the session every `interval`
+    /// until unsubscribed or until its shell exits. Subscribing again
+    /// replaces the interval.
+    pub async fn subscribe_resource_usage(
+        &self,
+        id: Uuid,
+        interval: Duration,
+        events: EventSink,
+    ) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            let interval_ms = interval.as_millis() as u64;
+            return daemon
+                .call_as(Request::SubscribeResourceUsage { id, interval_ms })
+                .await;
+        }
+
+        let pid = self.child_pid(id).await?;
+        let first = tokio::task::spawn_blocking(move || TreeSample::take(pid)).await;
+        let Ok(Ok(first)) = first else {
+            // The shell is gone.
+            return Ok(());
+        };
+        self.usage_subscriptions
+            .subscribe(id, pid, interval, first, events);
+        Ok(())
+    }
+
+    pub async fn unsubscribe_resource_usage(&self, id: Uuid) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            return daemon
+                .call_as(Request::UnsubscribeResourceUsage { id })
+                .await;
+        }
+
+        self.usage_subscriptions.unsubscribe(id);
+        Ok(())
+    }
+
+    /// Sets when the session reports `session-activity` and
+    /// `session-silence`, replacing its previous thresholds.
+    pub async fn set_monitor(
+        &self,
+        id: Uuid,
+        settings: MonitorSettings,
+        events: EventSink,
+    ) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::SetMonitor { id, settings }).await;
+        }
+
+        let shared = self.session(id).await?.shared.clone();
+        if let Ok(mut monitor) = shared.monitor.lock() {
+            monitor.configure(settings);
+        }
+
+        let mut watchers = self.silence_watchers.lock().await;
+        if let Some(old) = watchers.remove(&id) {
+            old.abort();
+        }
+        let Some(silence_after) = settings.silence_after() else {
+            return Ok(());
+        };
+
+        // Activity is noticed by the output task as output arrives;
+        // silence is the absence of output, so it needs a timer.
+        let silence_watchers = self.silence_watchers.clone();
+        let watcher = tokio::spawn(async move {
+            loop {
+                let deadline = shared
+                    .monitor
+                    .lock()
+                    .ok()
+                    .and_then(|monitor| monitor.silence_deadline());
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                    // Already reported; output restarts the clock, so the
+                    // next deadline is at least `silence_after` away.
+                    None => tokio::time::sleep(silence_after).await,
+                }
+                if shared.has_exited() {
+                    break;
+                }
+                let idle = shared
+                    .monitor
+                    .lock()
+                    .ok()
+                    .and_then(|mut monitor| monitor.check_silence(Instant::now()));
+                if let Some(idle) = idle {
+                    events.emit(
+                        "session-silence",
+                        SessionSilenceEvent {
+                            session_id: id.to_string(),
+                            idle_ms: idle.as_millis() as u64,
+                        },
+                    );
+                }
+            }
+            // Unless a newer watcher replaced this one meanwhile.
+            let mut watchers = silence_watchers.lock().await;
+            if watchers
+                .get(&id)
+                .is_some_and(|watcher| watcher.id() == tokio::task::id())
+            {
+                watchers.remove(&id);
+            }
+        });
+        watchers.insert(id, watcher);
+        Ok(())
+    }
+
+    /// Replaces the session's output triggers. Fails without changing
+    /// anything if a pattern doesn't compile.
+    pub async fn set_triggers(&self, id: Uuid, triggers: Vec<TriggerRule>) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::SetTriggers { id, triggers }).await;
+        }
+
+        let matcher = TriggerMatcher::new(triggers)?;
+        let session = self.session(id).await?;
+        if let Ok(mut triggers) = session.shared.triggers.lock() {
+            *triggers = matcher;
+        }
+        Ok(())
+    }
+
+    async fn session(&self, id: Uuid) -> Result<Arc<PtySession>> {
+        self.sessions
+            .lock()
+            .await
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Session not found"))
+    }
+
+    async fn child_pid(&self, id: Uuid) -> Result<i32> {
+        let session = self.session(id).await?;
+        session
+            .get_child_pid()
+            .map(|pid| pid as i32)
+            .ok_or_else(|| anyhow::anyhow!("Session PID unavailable"))
+    }
+
+    /// Starts teeing the session into an asciicast v2 file, replacing any
+    /// recording already in progress.
+    pub async fn start_recording(&self, id: Uuid, path: &Path, record_input: bool) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            let path = path.to_path_buf();
+            return daemon
+                .call_as(Request::StartRecording {
+                    id,
+                    path,
+                    record_input,
+                })
+                .await;
+        }
+
+        let session = self.session(id).await?;
+        let (cols, rows) = session.size();
+        let recorder = Recorder::start(path, cols, rows, record_input)?;
+        if let Ok(mut slot) = session.shared.recorder.lock() {
+            *slot = Some(recorder);
+        }
+        Ok(())
+    }
+
+    /// Stops the session's recording and returns the file it was written to.
+    pub async fn stop_recording(&self, id: Uuid) -> Result<Option<String>> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::StopRecording { id }).await;
+        }
+
+        let session = self.session(id).await?;
+        let recorder = session
+            .shared
+            .recorder
+            .lock()
+            .ok()
+            .and_then(|mut slot| slot.take());
+        let Some(recorder) = recorder else {
+            return Ok(None);
+        };
+        // The file is complete once this returns.
+        let path = tokio::task::spawn_blocking(move || recorder.finish()).await?;
+        Ok(Some(path.display().to_string()))
+    }
+
+    /// Starts a plain-text transcript of the session's output, replacing
+    /// any transcript already being written, and returns its path.
+    pub async fn start_transcript(&self, id: Uuid, config: &LoggingConfig) -> Result<String> {
+        if let Some(daemon) = &self.daemon {
+            let config = config.clone();
+            return daemon
+                .call_as(Request::StartTranscript { id, config })
+                .await;
+        }
+
+        let session = self.session(id).await?;
+        let logger = TranscriptLogger::start(config, id)?;
+        let path = logger.path().display().to_string();
+        if let Ok(mut slot) = session.shared.transcript.lock() {
+            *slot = Some(logger);
+        }
+        Ok(path)
+    }
+
+    /// Stops the session's transcript and returns the file it was written to.
+    pub async fn stop_transcript(&self, id: Uuid) -> Result<Option<String>> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::StopTranscript { id }).await;
+        }
+
+        let session = self.session(id).await?;
+        let logger = session
+            .shared
+            .transcript
+            .lock()
+            .ok()
+            .and_then(|mut slot| slot.take());
+        Ok(logger.map(|logger| logger.path().display().to_string()))
+    }
+
+    /// Streams the session's output to `channel`, starting with its
+    /// scrollback when `replay` is set. Playbacks stream their frames.
+    pub async fn attach_output(
+        &self,
+        id: Uuid,
+        channel: Channel,
+        replay: bool,
+        channels: &OutputChannels,
+    ) -> Result<()> {
+        if let Some(playback) = self.playbacks.lock().await.get(&id) {
+            channels.attach(id, channel);
+            playback.set_attached();
+            return Ok(());
+        }
+        if let Some(daemon) = &self.daemon {
+            if !replay {
+                channels.attach(id, channel);
+                return daemon.call_as(Request::StartFlowControl { id }).await;
+            }
+            // The replay comes back in line with the output; what the
+            // channel is sent before it is part of it.
+            channels.attach_awaiting_replay(id, channel);
+            return daemon.call_as(Request::AttachOutput { id }).await;
+        }
+
+        if replay {
+            return self
+                .attach_with_replay(id, |snapshot| {
+                    send_raw(&channel, snapshot);
+                    channels.attach(id, channel);
+                })
+                .await;
+        }
+        let session = self.session(id).await?;
+        channels.attach(id, channel);
+        session.shared.flow.start_acking();
+        Ok(())
+    }
+
+    /// Hands `attach` the scrollback and starts flow control, both under
+    /// the scrollback lock, so output sent afterwards continues exactly
+    /// where the replay ends. Output still queued is left out of the
+    /// replay: the next batch sends it to every terminal.
+    pub async fn attach_with_replay(&self, id: Uuid, attach: impl FnOnce(Vec<u8>)) -> Result<()> {
+        let session = self.session(id).await?;
+        let scrollback = session.shared.scrollback.lock();
+        let mut snapshot = match &scrollback {
+            Ok(scrollback) => scrollback.snapshot(),
+            Err(_) => Vec::new(),
+        };
+        let sent = snapshot
+            .len()
+            .saturating_sub(session.shared.flow.pending_len());
+        snapshot.truncate(sent);
+        attach(snapshot);
+        session.shared.flow.start_acking();
+        Ok(())
+    }
+
+    /// Holds back the session's output until the terminal acknowledges
+    /// what it was sent with `ack_output`.
+    pub async fn start_flow_control(&self, id: Uuid) -> Result<()> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::StartFlowControl { id }).await;
+        }
+
+        let session = self.session(id).await?;
+        session.shared.flow.start_acking();
+        Ok(())
+    }
+
+    /// The terminal rendered `bytes` more of the session's output.
+    pub async fn ack_output(&self, id: Uuid, bytes: usize) -> Result<()> {
+        // Playbacks aren't flow controlled.
+        if self.playbacks.lock().await.contains_key(&id) {
+            return Ok(());
+        }
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::AckOutput { id, bytes }).await;
+        }
+
+        let session = self.session(id).await?;
+        session.shared.flow.ack(bytes);
+        Ok(())
+    }
+
+    /// Holds back the output of a session whose tab is hidden, keeping
+    /// only its tail, and sends it in one batch once the tab is shown.
+    /// Activity, triggers, transcripts and the scrollback still see all
+    /// of it.
+    pub async fn set_visibility(&self, id: Uuid, visible: bool) -> Result<()> {
+        // Playbacks aren't flow controlled.
+        if self.playbacks.lock().await.contains_key(&id) {
+            return Ok(());
+        }
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::SetVisibility { id, visible }).await;
+        }
+
+        let session = self.session(id).await?;
+        session.shared.flow.set_visible(visible);
+        Ok(())
+    }
+
+    /// Stops holding back output for acknowledgements or hidden tabs, e.g.
+    /// because the window that sent them disconnected from the daemon.
+    pub async fn release_flow_control(&self) {
         let sessions = self.sessions.lock().await;
-        if let Some(session) = sessions.get(&id) {
-            let pid = session
-                .get_child_pid()
-                .ok_or_else(|| anyhow::anyhow!("Session PID unavailable"))?;
-            get_process_cwd(pid as i32)
-        } else {
-            Err(anyhow::anyhow!("Session not found"))
+        for session in sessions.values() {
+            session.shared.flow.stop_acking();
+            session.shared.flow.set_visible(true);
+        }
+    }
+
+    pub async fn replay(&self, id: Uuid) -> Result<Vec<u8>> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::Replay { id }).await;
         }
+
+        Ok(self.session(id).await?.replay())
+    }
+
+    pub async fn list(&self) -> Result<Vec<PtySessionInfo>> {
+        if let Some(daemon) = &self.daemon {
+            return daemon.call_as(Request::List).await;
+        }
+
+        let sessions = self.sessions.lock().await;
+        let mut infos: Vec<PtySessionInfo> = sessions
+            .values()
+            .map(|session| session_info(session))
+            .collect();
+        infos.sort_by_key(|info| info.created_at_ms);
+        Ok(infos)
+    }
+
+    /// Whether any session's child is still running. The daemon stays up
+    /// as long as this is true.
+    pub async fn has_live_sessions(&self) -> bool {
+        let sessions = self.sessions.lock().await;
+        sessions.values().any(|session| !session.has_exited())
     }
 
-    fn start_output_task(&self, id: Uuid, mut reader: Box<dyn Read + Send>, app: AppHandle) {
-        std::thread::spawn(move || {
+    fn start_output_task(
+        &self,
+        id: Uuid,
+        io: Arc<PtyIo>,
+        shared: Arc<SessionShared>,
+        input: InputQueue,
+        waiter: JoinHandle<ExitInfo>,
+        events: EventSink,
+    ) {
+        tokio::spawn(async move {
+            let flusher = tokio::spawn(flush_output(id, shared.clone(), events.clone()));
             let mut buf = [0u8; 8192];
-            let event_name = format!("pty-output-{}", id);
+            let mut scanner = OscScanner::new();
+            let mut osc_events = Vec::new();
+            let mut modes = ModeScanner::new();
+            let mut zmodem = ZmodemDetector::new();
+            let mut keep_whole = false;
+            let mut command_started: Option<Instant> = None;
+            let mut trigger_hits = Vec::new();
 
             loop {
-                match reader.read(&mut buf) {
+                match io.read(&mut buf).await {
                     Ok(0) => break,
                     Ok(n) => {
-                        let data = buf[..n].to_vec();
-                        let _ = app.emit(&event_name, data);
+                        shared.with_recorder(|recorder| recorder.output(&buf[..n]));
+                        shared.with_transcript(|transcript| transcript.output(&buf[..n]));
+                        shared.bytes_out.fetch_add(n as u64, Ordering::Relaxed);
+                        let quiet = shared
+                            .monitor
+                            .lock()
+                            .ok()
+                            .and_then(|mut monitor| monitor.output(Instant::now()));
+                        if let Some(quiet) = quiet {
+                            events.emit(
+                                "session-activity",
+                                SessionActivityEvent {
+                                    session_id: id.to_string(),
+                                    quiet_ms: quiet.as_millis() as u64,
+                                },
+                            );
+                        }
+
+                        if let Some(enabled) = modes.feed(&buf[..n]) {
+                            shared.bracketed_paste.store(enabled, Ordering::Relaxed);
+                        }
+
+                        scanner.feed(&buf[..n], &mut osc_events);
+                        for event in osc_events.drain(..) {
+                            handle_osc_event(&events, id, &shared, &mut command_started, event);
+                        }
+
+                        if let Ok(mut triggers) = shared.triggers.lock() {
+                            if let Some(matcher) = triggers.as_mut() {
+                                matcher.feed(&buf[..n], &mut trigger_hits);
+                            }
+                        }
+                        for hit in trigger_hits.drain(..) {
+                            handle_trigger_hit(&events, id, &input, hit);
+                        }
+
+                        // A hidden session must not trim full-screen
+                        // output or a transfer, from the chunk that starts
+                        // one through the chunk that ends it.
+                        let whole = zmodem.feed(&buf[..n]) || modes.alternate_screen();
+                        if whole && !keep_whole {
+                            shared.flow.set_keep_whole(true);
+                        }
+
+                        // Waits while the terminal is too far behind.
+                        shared.flow.wait_for_room().await;
+                        // Queued under the scrollback lock, so a terminal
+                        // that attaches with a replay gets every byte
+                        // exactly once.
+                        let mut scrollback = shared.scrollback.lock().ok();
+                        if let Some(scrollback) = scrollback.as_mut() {
+                            scrollback.push(&buf[..n]);
+                        }
+                        shared.flow.queue(&buf[..n]);
+                        drop(scrollback);
+
+                        if !whole && keep_whole {
+                            shared.flow.set_keep_whole(false);
+                        }
+                        keep_whole = whole;
                     }
                     Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
                     Err(err) => {
@@ -96,53 +848,156 @@ impl PtyManager {
                 }
             }
 
-            let _ = app.emit(&format!("pty-exit-{}", id), ());
-        });
-    }
-}
+            // The child is gone; nobody will read what is still queued.
+            input.close();
 
-#[cfg(target_os = "macos")]
-fn get_process_cwd(pid: i32) -> Result<String> {
-    use std::process::Command;
+            // Deliver the last output before reporting the exit.
+            shared.flow.close();
+            let _ = flusher.await;
 
-    // Use lsof to get the current working directory
-    let output = Command::new("lsof")
-        .args(&["-p", &pid.to_string(), "-a", "-d", "cwd", "-Fn"])
-        .output()
-        .map_err(|e| anyhow::anyhow!("Failed to run lsof: {}", e))?;
+            // Dropping the recorder and transcript lets their writers flush
+            // and close the files.
+            if let Ok(mut recorder) = shared.recorder.lock() {
+                recorder.take();
+            }
+            if let Ok(mut transcript) = shared.transcript.lock() {
+                transcript.take();
+            }
 
-    if !output.status.success() {
-        return Err(anyhow::anyhow!("lsof command failed"));
+            // The reader hits EOF once every holder of the slave side is gone,
+            // which is usually just after the child exits.
+            match waiter.await {
+                Ok(info) => {
+                    events.emit(&format!("pty-exit-{}", id), info);
+                }
+                Err(_) => {
+                    eprintln!("PTY waiter task for {} panicked", id);
+                    events.emit(&format!("pty-exit-{}", id), ());
+                }
+            }
+        });
     }
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-
-    // Parse lsof output - format is:
-    // p<pid>
-    // n<path>
-    for line in stdout.lines() {
-        if line.starts_with('n') {
-            let path = &line[1..]; // Skip the 'n' prefix
-            return Ok(path.to_string());
+/// Sends the session's output in the batches `OutputFlow` coalesces reads
+/// into, until the reader closes the flow.
+async fn flush_output(id: Uuid, shared: Arc<SessionShared>, events: EventSink) {
+    while shared.flow.batch_due().await {
+        // Taken and sent under the scrollback lock, like it was queued.
+        let scrollback = shared.scrollback.lock();
+        let Some(batch) = shared.flow.take_batch() else {
+            continue;
+        };
+        if !events.output(id, &batch) {
+            // Nobody will acknowledge it; don't wait for them.
+            shared.flow.stop_acking();
         }
+        drop(scrollback);
     }
+}
+
+fn session_info(session: &PtySession) -> PtySessionInfo {
+    let (command, args) = match session.pty.argv.split_first() {
+        Some((command, args)) => (command.clone(), args.to_vec()),
+        None => (String::new(), Vec::new()),
+    };
+    let created_at_ms = session
+        .created_at
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0);
+    let (cols, rows) = session.size();
 
-    Err(anyhow::anyhow!("Could not find cwd in lsof output"))
+    PtySessionInfo {
+        id: session.id.to_string(),
+        command,
+        args,
+        pid: session.pty.pid,
+        cols,
+        rows,
+        created_at_ms,
+        bytes_in: session.shared.bytes_in.load(Ordering::Relaxed),
+        bytes_out: session.shared.bytes_out.load(Ordering::Relaxed),
+        alive: !session.has_exited(),
+    }
 }
 
-#[cfg(target_os = "linux")]
-fn get_process_cwd(pid: i32) -> Result<String> {
-    let cwd_path = format!("/proc/{}/cwd", pid);
-    std::fs::read_link(&cwd_path)
-        .map(|p| p.display().to_string())
-        .map_err(|e| anyhow::anyhow!("Failed to read /proc/{}/cwd: {}", pid, e))
+/// Emits `session-trigger` and writes the trigger's response, if any, to
+/// the session alone; responses are not mirrored to input groups. The
+/// reader can't wait for room in the input queue, since the child may be
+/// blocked on output only the reader drains.
+fn handle_trigger_hit(events: &EventSink, id: Uuid, input: &InputQueue, hit: TriggerHit) {
+    let responded = hit
+        .respond
+        .is_some_and(|response| input.try_send(response.as_bytes()).is_ok());
+    events.emit(
+        "session-trigger",
+        SessionTriggerEvent {
+            session_id: id.to_string(),
+            pattern: hit.pattern,
+            line: hit.line,
+            matched: hit.matched,
+            responded,
+        },
+    );
 }
 
-#[cfg(not(any(target_os = "macos", target_os = "linux")))]
-fn get_process_cwd(_pid: i32) -> Result<String> {
-    Err(anyhow::anyhow!(
-        "Getting process CWD is not supported on this platform"
-    ))
+/// Turns shell integration marks into `session-cwd-changed`,
+/// `command-started` and `command-finished` events.
+fn handle_osc_event(
+    events: &EventSink,
+    id: Uuid,
+    shared: &SessionShared,
+    command_started: &mut Option<Instant>,
+    event: OscEvent,
+) {
+    match event {
+        OscEvent::Cwd { host, path } => {
+            let reported = ReportedCwd {
+                host: host.clone(),
+                path: path.clone(),
+            };
+            if let Ok(mut cwd) = shared.reported_cwd.lock() {
+                if cwd.as_ref() == Some(&reported) {
+                    return;
+                }
+                *cwd = Some(reported);
+            }
+            events.emit(
+                "session-cwd-changed",
+                CwdChangedEvent {
+                    session_id: id.to_string(),
+                    cwd: path,
+                    host,
+                },
+            );
+        }
+        OscEvent::CommandStart => {
+            *command_started = Some(Instant::now());
+            events.emit(
+                "command-started",
+                CommandStartedEvent {
+                    session_id: id.to_string(),
+                },
+            );
+        }
+        OscEvent::CommandFinished { exit_code } => {
+            // Shells also send D for an empty command line; without a
+            // matching C there is nothing to report.
+            let Some(started) = command_started.take() else {
+                return;
+            };
+            events.emit(
+                "command-finished",
+                CommandFinishedEvent {
+                    session_id: id.to_string(),
+                    exit_code,
+                    duration_ms: started.elapsed().as_millis() as u64,
+                },
+            );
+        }
+        OscEvent::PromptStart => {}
+    }
 }
 
 impl Default for PtyManager {
@@ -1,3 +1,25 @@
+pub mod bench;
+pub mod env;
+pub mod events;
+pub mod exit;
+pub mod flow;
+pub mod groups;
+pub mod input;
 pub mod manager;
+pub mod modes;
+pub mod monitor;
+pub mod osc;
+pub mod output;
+pub mod playback;
+pub mod process;
+pub mod pty_io;
+pub mod recording;
+pub mod scrollback;
 pub mod session;
+pub mod shell_integration;
+pub mod signal;
+pub mod transcript;
+pub mod triggers;
 pub mod unix_pty;
+pub mod usage;
+pub mod zmodem;
@@ -0,0 +1,123 @@
+/// Longest CSI parameter string we keep. Mode switches are short; anything
+/// longer cannot be one.
+const MAX_PARAMS_LEN: usize = 32;
+
+const ESC: u8 = 0x1b;
+
+/// DECSET mode a program enables to have pastes wrapped in `ESC [200~` and
+/// `ESC [201~`.
+const BRACKETED_PASTE: &[u8] = b"2004";
+
+/// DECSET modes that switch to the alternate screen of full-screen
+/// programs.
+const ALTERNATE_SCREEN: &[&[u8]] = &[b"1049", b"1047", b"47"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    Csi,
+}
+
+/// Incremental scanner that follows the bracketed paste mode a program
+/// switches with `CSI ? 2004 h` / `CSI ? 2004 l` in its output, and
+/// whether it is on the alternate screen.
+///
+/// Like `OscScanner` it survives sequences split across reads and only
+/// searches for ESC outside of a sequence.
+pub struct ModeScanner {
+    state: State,
+    params: Vec<u8>,
+    alternate_screen: bool,
+}
+
+impl ModeScanner {
+    pub fn new() -> Self {
+        Self {
+            state: State::Ground,
+            params: Vec::new(),
+            alternate_screen: false,
+        }
+    }
+
+    /// Whether a full-screen program switched to the alternate screen.
+    pub fn alternate_screen(&self) -> bool {
+        self.alternate_screen
+    }
+
+    /// Returns the bracketed paste mode after `data` if `data` switched it.
+    pub fn feed(&mut self, data: &[u8]) -> Option<bool> {
+        let mut bracketed_paste = None;
+        let mut i = 0;
+
+        while i < data.len() {
+            match self.state {
+                State::Ground => match data[i..].iter().position(|&b| b == ESC) {
+                    Some(offset) => {
+                        i += offset + 1;
+                        self.state = State::Escape;
+                    }
+                    None => break,
+                },
+                State::Escape => {
+                    match data[i] {
+                        b'[' => {
+                            self.params.clear();
+                            self.state = State::Csi;
+                        }
+                        ESC => {}
+                        _ => self.state = State::Ground,
+                    }
+                    i += 1;
+                }
+                State::Csi => {
+                    match data[i] {
+                        // Parameter and intermediate bytes.
+                        byte @ 0x20..=0x3f if self.params.len() < MAX_PARAMS_LEN => {
+                            self.params.push(byte);
+                        }
+                        0x20..=0x3f => {}
+                        final_byte @ 0x40..=0x7e => {
+                            if let Some(enabled) = self.mode_switch(final_byte) {
+                                bracketed_paste = Some(enabled);
+                            }
+                            self.state = State::Ground;
+                        }
+                        ESC => self.state = State::Escape,
+                        // C0 controls inside a CSI are executed by the
+                        // terminal and don't end the sequence.
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+        }
+
+        bracketed_paste
+    }
+
+    fn mode_switch(&mut self, final_byte: u8) -> Option<bool> {
+        let enabled = match final_byte {
+            b'h' => true,
+            b'l' => false,
+            _ => return None,
+        };
+        let modes = self.params.strip_prefix(b"?")?;
+        if modes
+            .split(|&b| b == b';')
+            .any(|mode| ALTERNATE_SCREEN.contains(&mode))
+        {
+            self.alternate_screen = enabled;
+        }
+        modes
+            .split(|&b| b == b';')
+            .any(|mode| mode == BRACKETED_PASTE)
+            .then_some(enabled)
+    }
+}
+
+impl Default for ModeScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
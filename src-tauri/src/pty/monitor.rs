@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Per-session thresholds for `session-activity` and `session-silence`,
+/// in the spirit of tmux's monitor-activity and monitor-silence. `None`
+/// turns the respective event off.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MonitorSettings {
+    /// How long the session has to be quiet before new output counts as
+    /// activity.
+    pub activity_after_ms: Option<u64>,
+    /// How long the session has to be quiet before it counts as silent.
+    pub silence_after_ms: Option<u64>,
+}
+
+impl MonitorSettings {
+    fn activity_after(&self) -> Option<Duration> {
+        self.activity_after_ms.map(Duration::from_millis)
+    }
+
+    pub fn silence_after(&self) -> Option<Duration> {
+        self.silence_after_ms.map(Duration::from_millis)
+    }
+}
+
+/// When a session last produced output, and what has been reported about
+/// it since. Fed by the output thread; the silence watcher polls it.
+pub struct ActivityMonitor {
+    settings: MonitorSettings,
+    last_output: Instant,
+    silence_reported: bool,
+}
+
+impl ActivityMonitor {
+    pub fn new() -> Self {
+        Self {
+            settings: MonitorSettings::default(),
+            last_output: Instant::now(),
+            silence_reported: false,
+        }
+    }
+
+    /// Replaces the thresholds. The quiet period so far still counts.
+    pub fn configure(&mut self, settings: MonitorSettings) {
+        self.settings = settings;
+        self.silence_reported = false;
+    }
+
+    /// Records output at `now`. Returns how long the session had been quiet
+    /// if that makes this output activity worth reporting.
+    pub fn output(&mut self, now: Instant) -> Option<Duration> {
+        let quiet = now.saturating_duration_since(self.last_output);
+        self.last_output = now;
+        self.silence_reported = false;
+
+        let threshold = self.settings.activity_after()?;
+        (quiet >= threshold).then_some(quiet)
+    }
+
+    /// When the session will count as silent, unless output arrives first.
+    /// `None` once silence has been reported for the current quiet period.
+    pub fn silence_deadline(&self) -> Option<Instant> {
+        if self.silence_reported {
+            return None;
+        }
+        Some(self.last_output + self.settings.silence_after()?)
+    }
+
+    /// Returns how long the session has been quiet if it just became silent
+    /// at `now`. Reports each quiet period only once.
+    pub fn check_silence(&mut self, now: Instant) -> Option<Duration> {
+        if now < self.silence_deadline()? {
+            return None;
+        }
+        self.silence_reported = true;
+        Some(now.saturating_duration_since(self.last_output))
+    }
+}
+
+impl Default for ActivityMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,203 @@
+/// Longest OSC payload we buffer. Anything longer (OSC 52 clipboard data,
+/// inline images, ...) is not one of ours and is skipped.
+const MAX_OSC_LEN: usize = 4096;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Shell integration sequences recognised in the output stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OscEvent {
+    /// OSC 7: the shell reported its working directory.
+    Cwd { host: Option<String>, path: String },
+    /// OSC 133;A: a new prompt is being drawn.
+    PromptStart,
+    /// OSC 133;C: the command line was submitted and the command is running.
+    CommandStart,
+    /// OSC 133;D: the command finished, with its exit status if reported.
+    CommandFinished { exit_code: Option<i32> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    Osc,
+    /// Saw ESC inside an OSC; `\` completes the ST terminator.
+    OscEscape,
+}
+
+/// Incremental scanner for OSC 7 and OSC 133 sequences.
+///
+/// Output arrives in arbitrary chunks, so a sequence may be split across
+/// reads; the scanner keeps just enough state to resume. It never modifies
+/// the stream and outside of an OSC it only searches for ESC bytes, which
+/// keeps it cheap on bulk output.
+pub struct OscScanner {
+    state: State,
+    payload: Vec<u8>,
+    /// The current OSC is too long or not one we care about.
+    skipping: bool,
+}
+
+impl OscScanner {
+    pub fn new() -> Self {
+        Self {
+            state: State::Ground,
+            payload: Vec::new(),
+            skipping: false,
+        }
+    }
+
+    pub fn feed(&mut self, data: &[u8], events: &mut Vec<OscEvent>) {
+        let mut i = 0;
+
+        while i < data.len() {
+            match self.state {
+                State::Ground => match data[i..].iter().position(|&b| b == ESC) {
+                    Some(offset) => {
+                        i += offset + 1;
+                        self.state = State::Escape;
+                    }
+                    None => return,
+                },
+                State::Escape => {
+                    match data[i] {
+                        b']' => {
+                            self.payload.clear();
+                            self.skipping = false;
+                            self.state = State::Osc;
+                        }
+                        ESC => {}
+                        _ => self.state = State::Ground,
+                    }
+                    i += 1;
+                }
+                State::Osc => match data[i..].iter().position(|&b| b == BEL || b == ESC) {
+                    Some(offset) => {
+                        self.push(&data[i..i + offset]);
+                        let terminator = data[i + offset];
+                        i += offset + 1;
+                        if terminator == BEL {
+                            self.finish(events);
+                        } else {
+                            self.state = State::OscEscape;
+                        }
+                    }
+                    None => {
+                        self.push(&data[i..]);
+                        return;
+                    }
+                },
+                State::OscEscape => {
+                    if data[i] == b'\\' {
+                        self.finish(events);
+                        i += 1;
+                    } else {
+                        // ESC followed by anything else aborts the OSC and
+                        // starts a new escape sequence.
+                        self.state = State::Escape;
+                    }
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        if self.skipping {
+            return;
+        }
+
+        self.payload.extend_from_slice(bytes);
+        if self.payload.len() > MAX_OSC_LEN || !could_be_ours(&self.payload) {
+            self.skipping = true;
+            self.payload.clear();
+        }
+    }
+
+    fn finish(&mut self, events: &mut Vec<OscEvent>) {
+        self.state = State::Ground;
+        if !self.skipping {
+            if let Some(event) = parse_osc(&self.payload) {
+                events.push(event);
+            }
+        }
+        self.payload.clear();
+    }
+}
+
+impl Default for OscScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether a (possibly partial) payload can still turn into OSC 7 or 133.
+fn could_be_ours(payload: &[u8]) -> bool {
+    ["7;".as_bytes(), "133;".as_bytes()].iter().any(|prefix| {
+        let len = payload.len().min(prefix.len());
+        payload[..len] == prefix[..len]
+    })
+}
+
+fn parse_osc(payload: &[u8]) -> Option<OscEvent> {
+    let payload = std::str::from_utf8(payload).ok()?;
+
+    if let Some(url) = payload.strip_prefix("7;") {
+        return parse_cwd_url(url);
+    }
+
+    let mark = payload.strip_prefix("133;")?;
+    let mut params = mark.split(';');
+    match params.next()? {
+        "A" => Some(OscEvent::PromptStart),
+        "C" => Some(OscEvent::CommandStart),
+        "D" => Some(OscEvent::CommandFinished {
+            exit_code: params.next().and_then(|code| code.trim().parse().ok()),
+        }),
+        _ => None,
+    }
+}
+
+/// Parses the `file://host/path` URL of an OSC 7 report.
+fn parse_cwd_url(url: &str) -> Option<OscEvent> {
+    let (host, path) = match url
+        .strip_prefix("file://")
+        .or_else(|| url.strip_prefix("kitty-shell-cwd://"))
+    {
+        Some(rest) => {
+            let slash = rest.find('/')?;
+            let host = &rest[..slash];
+            let host = (!host.is_empty()).then(|| host.to_string());
+            (host, &rest[slash..])
+        }
+        None if url.starts_with('/') => (None, url),
+        None => return None,
+    };
+
+    Some(OscEvent::Cwd {
+        host,
+        path: percent_decode(path),
+    })
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(value) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                out.push(value);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
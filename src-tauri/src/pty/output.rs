@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::ipc::{Channel, InvokeResponseBody};
+use uuid::Uuid;
+
+/// The channels terminals stream session output from, by session.
+///
+/// Output goes to the webview as raw binary messages instead of events,
+/// which Tauri would serialize as a JSON array of numbers per chunk.
+#[derive(Default)]
+pub struct OutputChannels {
+    channels: Mutex<HashMap<Uuid, Terminal>>,
+}
+
+struct Terminal {
+    channel: Channel,
+    /// Output is part of the replay the terminal is still waiting for, so
+    /// it is dropped until the replay arrives.
+    awaiting_replay: bool,
+}
+
+impl OutputChannels {
+    /// Sends the session's output to `channel` from now on, replacing the
+    /// channel of a terminal that was unmounted.
+    pub fn attach(&self, id: Uuid, channel: Channel) {
+        self.insert(id, channel, false);
+    }
+
+    /// Like `attach`, for a terminal whose replay arrives in line with the
+    /// output, as it does from the session daemon.
+    pub fn attach_awaiting_replay(&self, id: Uuid, channel: Channel) {
+        self.insert(id, channel, true);
+    }
+
+    /// Sends the terminal waiting for it its replay; output that follows
+    /// goes through.
+    pub fn replay(&self, id: Uuid, data: Vec<u8>) {
+        let Ok(mut channels) = self.channels.lock() else {
+            return;
+        };
+        if let Some(terminal) = channels.get_mut(&id) {
+            if terminal.awaiting_replay {
+                terminal.awaiting_replay = false;
+                send_raw(&terminal.channel, data);
+            }
+        }
+    }
+
+    fn insert(&self, id: Uuid, channel: Channel, awaiting_replay: bool) {
+        if let Ok(mut channels) = self.channels.lock() {
+            channels.insert(
+                id,
+                Terminal {
+                    channel,
+                    awaiting_replay,
+                },
+            );
+        }
+    }
+
+    pub fn detach(&self, id: Uuid) {
+        if let Ok(mut channels) = self.channels.lock() {
+            channels.remove(&id);
+        }
+    }
+
+    /// Output of a session without a terminal is dropped; the terminal
+    /// replays the scrollback when it attaches. Returns whether a terminal
+    /// got it.
+    pub fn send(&self, id: Uuid, data: &[u8]) -> bool {
+        let Ok(mut channels) = self.channels.lock() else {
+            return false;
+        };
+        let Some(terminal) = channels.get(&id) else {
+            return false;
+        };
+        if terminal.awaiting_replay {
+            return false;
+        }
+        if terminal
+            .channel
+            .send(InvokeResponseBody::Raw(data.to_vec()))
+            .is_err()
+        {
+            // The webview side of the channel is gone.
+            channels.remove(&id);
+            return false;
+        }
+        true
+    }
+}
+
+/// Sends `data` on a channel that isn't registered yet, e.g. the replay a
+/// terminal receives before live output.
+pub fn send_raw(channel: &Channel, data: Vec<u8>) {
+    if !data.is_empty() {
+        let _ = channel.send(InvokeResponseBody::Raw(data));
+    }
+}
@@ -0,0 +1,308 @@
+use super::events::EventSink;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+/// Resets the terminal before re-rendering everything up to a seek target.
+const RESET_TERMINAL: &[u8] = b"\x1bc";
+
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 16.0;
+
+struct CastFrame {
+    time: f64,
+    kind: FrameKind,
+}
+
+enum FrameKind {
+    Output(String),
+    Resize(u16, u16),
+}
+
+struct Cast {
+    width: u16,
+    height: u16,
+    /// Longest pause between frames, from the header's `idle_time_limit`.
+    idle_time_limit: Option<f64>,
+    frames: Vec<CastFrame>,
+}
+
+impl Cast {
+    fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+
+        let header: Value = serde_json::from_str(
+            lines
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Recording is empty"))?,
+        )
+        .map_err(|e| anyhow::anyhow!("Invalid asciicast header: {}", e))?;
+
+        if header.get("version").and_then(Value::as_u64) != Some(2) {
+            return Err(anyhow::anyhow!(
+                "Only asciicast v2 recordings are supported"
+            ));
+        }
+
+        let dimension = |key: &str| {
+            header
+                .get(key)
+                .and_then(Value::as_u64)
+                .map(|value| value.min(u16::MAX as u64) as u16)
+        };
+
+        let mut frames = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let event: (f64, String, String) = serde_json::from_str(line).map_err(|e| {
+                anyhow::anyhow!("Invalid asciicast event on line {}: {}", index + 2, e)
+            })?;
+            let (time, code, data) = event;
+
+            let kind = match code.as_str() {
+                "o" => FrameKind::Output(data),
+                "r" => match data.split_once('x') {
+                    Some((cols, rows)) => match (cols.parse(), rows.parse()) {
+                        (Ok(cols), Ok(rows)) => FrameKind::Resize(cols, rows),
+                        _ => continue,
+                    },
+                    None => continue,
+                },
+                // Input and marker events are not rendered.
+                _ => continue,
+            };
+            frames.push(CastFrame { time, kind });
+        }
+
+        Ok(Self {
+            width: dimension("width").unwrap_or(80),
+            height: dimension("height").unwrap_or(24),
+            idle_time_limit: header.get("idle_time_limit").and_then(Value::as_f64),
+            frames,
+        })
+    }
+
+    fn duration(&self) -> f64 {
+        self.frames.last().map_or(0.0, |frame| frame.time)
+    }
+}
+
+/// Sent as `playback-state-{id}` whenever playback starts, pauses, resumes,
+/// seeks, changes speed, resizes or reaches the end.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackState {
+    pub position: f64,
+    pub duration: f64,
+    pub speed: f64,
+    pub paused: bool,
+    pub finished: bool,
+    pub cols: u16,
+    pub rows: u16,
+}
+
+#[derive(Clone, Copy)]
+struct Control {
+    paused: bool,
+    speed: f64,
+    seek_to: Option<f64>,
+    /// A terminal is attached; until then frames would be dropped, so
+    /// playback doesn't start.
+    attached: bool,
+    stopped: bool,
+}
+
+/// A read-only session that plays an asciicast recording through the same
+/// output channel as a live PTY. Playback runs on its own thread, starts
+/// once a terminal attaches and stops when the handle is dropped.
+pub struct Playback {
+    control: Arc<(Mutex<Control>, Condvar)>,
+}
+
+impl Playback {
+    pub fn start(id: Uuid, path: &Path, speed: f64, app: AppHandle) -> Result<Self> {
+        let cast = Cast::load(path)?;
+        let control = Arc::new((
+            Mutex::new(Control {
+                paused: false,
+                speed: speed.clamp(MIN_SPEED, MAX_SPEED),
+                seek_to: None,
+                attached: false,
+                stopped: false,
+            }),
+            Condvar::new(),
+        ));
+
+        let thread_control = control.clone();
+        std::thread::spawn(move || play(id, cast, thread_control, app));
+
+        Ok(Self { control })
+    }
+
+    /// A terminal streams the frames now; playback starts if it hadn't.
+    pub fn set_attached(&self) {
+        self.update(|control| control.attached = true);
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.update(|control| control.paused = paused);
+    }
+
+    pub fn seek(&self, position: f64) {
+        self.update(|control| control.seek_to = Some(position.max(0.0)));
+    }
+
+    pub fn set_speed(&self, speed: f64) {
+        self.update(|control| control.speed = speed.clamp(MIN_SPEED, MAX_SPEED));
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Control)) {
+        let (lock, condvar) = &*self.control;
+        if let Ok(mut control) = lock.lock() {
+            f(&mut control);
+            condvar.notify_all();
+        }
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        self.update(|control| control.stopped = true);
+    }
+}
+
+fn play(id: Uuid, cast: Cast, control: Arc<(Mutex<Control>, Condvar)>, app: AppHandle) {
+    let output = EventSink::from(app.clone());
+    let state_event = format!("playback-state-{}", id);
+    let duration = cast.duration();
+
+    // Recording time of the last rendered frame and the index of the next.
+    let mut position = 0.0;
+    let mut index = 0;
+    let (mut cols, mut rows) = (cast.width, cast.height);
+    let mut finished = false;
+
+    let (lock, condvar) = &*control;
+    let emit_state = |control: &Control, position: f64, finished: bool, cols: u16, rows: u16| {
+        let _ = app.emit(
+            &state_event,
+            PlaybackState {
+                position,
+                duration,
+                speed: control.speed,
+                paused: control.paused,
+                finished,
+                cols,
+                rows,
+            },
+        );
+    };
+    let settings = lock.lock().and_then(|guard| {
+        condvar.wait_while(guard, |control| !control.attached && !control.stopped)
+    });
+    let Ok(settings) = settings.map(|guard| *guard) else {
+        return;
+    };
+    if settings.stopped {
+        return;
+    }
+    emit_state(&settings, position, finished, cols, rows);
+    let mut last_reported = (settings.paused, settings.speed);
+
+    // Frames and state are sent with the lock released, so a slow consumer
+    // never holds up the controls.
+    loop {
+        let Ok(mut guard) = lock.lock() else {
+            return;
+        };
+        if guard.stopped {
+            return;
+        }
+
+        if let Some(target) = guard.seek_to.take() {
+            let settings = *guard;
+            drop(guard);
+            let target = target.min(duration);
+            let mut chunk = RESET_TERMINAL.to_vec();
+            index = 0;
+            while index < cast.frames.len() && cast.frames[index].time <= target {
+                match &cast.frames[index].kind {
+                    FrameKind::Output(data) => chunk.extend_from_slice(data.as_bytes()),
+                    FrameKind::Resize(c, r) => (cols, rows) = (*c, *r),
+                }
+                index += 1;
+            }
+            position = target;
+            finished = index >= cast.frames.len();
+            output.output(id, &chunk);
+            emit_state(&settings, position, finished, cols, rows);
+            last_reported = (settings.paused, settings.speed);
+            continue;
+        }
+
+        if (guard.paused, guard.speed) != last_reported {
+            let settings = *guard;
+            drop(guard);
+            last_reported = (settings.paused, settings.speed);
+            emit_state(&settings, position, finished, cols, rows);
+            continue;
+        }
+
+        if guard.paused || index >= cast.frames.len() {
+            if index >= cast.frames.len() && !finished {
+                let settings = *guard;
+                drop(guard);
+                finished = true;
+                emit_state(&settings, position, finished, cols, rows);
+                continue;
+            }
+            if condvar.wait(guard).is_err() {
+                return;
+            }
+            continue;
+        }
+
+        let frame = &cast.frames[index];
+        let mut gap = (frame.time - position).max(0.0);
+        if let Some(limit) = cast.idle_time_limit {
+            gap = gap.min(limit);
+        }
+        let speed = guard.speed;
+        let wait = Duration::from_secs_f64(gap / speed);
+
+        if !wait.is_zero() {
+            let waited_from = Instant::now();
+            let (next_guard, timeout) = match condvar.wait_timeout(guard, wait) {
+                Ok(result) => result,
+                Err(_) => return,
+            };
+            guard = next_guard;
+            if !timeout.timed_out() {
+                // Woken by a control change: the time that did pass was
+                // played at the old speed. Re-evaluate with the new settings.
+                let played = waited_from.elapsed().as_secs_f64() * speed;
+                position = (position + played).min(frame.time);
+                continue;
+            }
+        }
+
+        let settings = *guard;
+        drop(guard);
+        match &frame.kind {
+            FrameKind::Output(data) => {
+                output.output(id, data.as_bytes());
+            }
+            FrameKind::Resize(c, r) => {
+                (cols, rows) = (*c, *r);
+                emit_state(&settings, frame.time, finished, cols, rows);
+            }
+        }
+        position = frame.time;
+        index += 1;
+    }
+}
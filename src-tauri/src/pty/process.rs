@@ -0,0 +1,205 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Snapshot of a single process, as reported by `get_session_foreground`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: i32,
+    pub name: String,
+    pub cmdline: Vec<String>,
+    pub cwd: Option<String>,
+    /// Process start time in milliseconds since the Unix epoch.
+    pub start_time_ms: Option<u64>,
+    /// True when the foreground process is the session's shell itself.
+    pub is_shell: bool,
+}
+
+pub fn inspect_process(pid: i32, shell_pid: Option<u32>) -> Result<ProcessInfo> {
+    let name = get_process_name(pid)?;
+
+    Ok(ProcessInfo {
+        pid,
+        name,
+        cmdline: get_process_cmdline(pid).unwrap_or_default(),
+        cwd: get_process_cwd(pid).ok(),
+        start_time_ms: get_process_start_time_ms(pid).ok(),
+        is_shell: shell_pid.is_some_and(|shell| shell as i32 == pid),
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub fn get_process_cwd(pid: i32) -> Result<String> {
+    use std::process::Command;
+
+    // Use lsof to get the current working directory
+    let output = Command::new("lsof")
+        .args(&["-p", &pid.to_string(), "-a", "-d", "cwd", "-Fn"])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run lsof: {}", e))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("lsof command failed"));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Parse lsof output - format is:
+    // p<pid>
+    // n<path>
+    for line in stdout.lines() {
+        if line.starts_with('n') {
+            let path = &line[1..]; // Skip the 'n' prefix
+            return Ok(path.to_string());
+        }
+    }
+
+    Err(anyhow::anyhow!("Could not find cwd in lsof output"))
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_process_cwd(pid: i32) -> Result<String> {
+    let cwd_path = format!("/proc/{}/cwd", pid);
+    std::fs::read_link(&cwd_path)
+        .map(|p| p.display().to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to read /proc/{}/cwd: {}", pid, e))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn get_process_cwd(_pid: i32) -> Result<String> {
+    Err(anyhow::anyhow!(
+        "Getting process CWD is not supported on this platform"
+    ))
+}
+
+/// Whether the host of an OSC 7 report is this machine. Shells report
+/// `hostname`, with or without the domain.
+pub fn is_local_host(host: &str) -> bool {
+    if host.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    let short = |name: &str| {
+        name.split('.')
+            .next()
+            .unwrap_or(name)
+            .to_ascii_lowercase()
+    };
+    local_hostname().is_some_and(|local| short(host) == short(&local))
+}
+
+#[cfg(unix)]
+fn local_hostname() -> Option<String> {
+    let mut name = [0u8; 256];
+    if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } != 0 {
+        return None;
+    }
+    let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    Some(String::from_utf8_lossy(&name[..len]).into_owned())
+}
+
+#[cfg(not(unix))]
+fn local_hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+#[cfg(target_os = "linux")]
+fn get_process_name(pid: i32) -> Result<String> {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .map(|name| name.trim_end().to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to read /proc/{}/comm: {}", pid, e))
+}
+
+#[cfg(target_os = "linux")]
+fn get_process_cmdline(pid: i32) -> Result<Vec<String>> {
+    let raw = std::fs::read(format!("/proc/{}/cmdline", pid))
+        .map_err(|e| anyhow::anyhow!("Failed to read /proc/{}/cmdline: {}", pid, e))?;
+
+    Ok(raw
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect())
+}
+
+#[cfg(target_os = "linux")]
+fn get_process_start_time_ms(pid: i32) -> Result<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        .map_err(|e| anyhow::anyhow!("Failed to read /proc/{}/stat: {}", pid, e))?;
+
+    // The command name is wrapped in parentheses and may itself contain
+    // spaces or parentheses, so start counting fields after the last ')'.
+    // `starttime` is field 22 overall, i.e. the 20th after the name.
+    let start_ticks: u64 = stat
+        .rsplit_once(')')
+        .and_then(|(_, rest)| rest.split_whitespace().nth(19))
+        .and_then(|field| field.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("Malformed /proc/{}/stat", pid))?;
+
+    let boot_time_secs: u64 = std::fs::read_to_string("/proc/stat")?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|value| value.trim().parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("btime missing from /proc/stat"))?;
+
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_sec <= 0 {
+        return Err(anyhow::anyhow!("Invalid clock tick rate"));
+    }
+
+    Ok(boot_time_secs * 1000 + start_ticks * 1000 / ticks_per_sec as u64)
+}
+
+#[cfg(target_os = "macos")]
+fn get_process_name(pid: i32) -> Result<String> {
+    libproc::proc_pid::name(pid).map_err(|e| anyhow::anyhow!("Failed to get process name: {}", e))
+}
+
+#[cfg(target_os = "macos")]
+fn get_process_cmdline(pid: i32) -> Result<Vec<String>> {
+    use std::process::Command;
+
+    // ps joins argv with spaces, so arguments containing spaces are split.
+    let output = Command::new("ps")
+        .args(["-o", "command=", "-p", &pid.to_string()])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run ps: {}", e))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("ps command failed"));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .map(|arg| arg.to_string())
+        .collect())
+}
+
+#[cfg(target_os = "macos")]
+fn get_process_start_time_ms(pid: i32) -> Result<u64> {
+    use libproc::bsd_info::BSDInfo;
+    use libproc::proc_pid::pidinfo;
+
+    let info = pidinfo::<BSDInfo>(pid, 0)
+        .map_err(|e| anyhow::anyhow!("Failed to get process info: {}", e))?;
+    Ok(info.pbi_start_tvsec * 1000 + info.pbi_start_tvusec / 1000)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn get_process_name(_pid: i32) -> Result<String> {
+    Err(anyhow::anyhow!(
+        "Inspecting processes is not supported on this platform"
+    ))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn get_process_cmdline(_pid: i32) -> Result<Vec<String>> {
+    Err(anyhow::anyhow!(
+        "Inspecting processes is not supported on this platform"
+    ))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn get_process_start_time_ms(_pid: i32) -> Result<u64> {
+    Err(anyhow::anyhow!(
+        "Inspecting processes is not supported on this platform"
+    ))
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use portable_pty::MasterPty;
+use std::io;
+#[cfg(not(unix))]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+#[cfg(not(unix))]
+use std::sync::{Arc, Mutex};
+#[cfg(unix)]
+use tokio::io::{unix::AsyncFd, Interest};
+
+/// A session's PTY master, read and written from tasks on the tokio
+/// runtime rather than from threads blocked in `read` and `write`, so
+/// open sessions cost no threads.
+///
+/// On Unix the master is a non-blocking fd registered with the runtime's
+/// reactor.
+#[cfg(unix)]
+pub struct PtyIo {
+    fd: AsyncFd<OwnedFd>,
+}
+
+#[cfg(unix)]
+impl PtyIo {
+    /// Has to be called on the runtime.
+    pub fn new(master: &dyn MasterPty) -> Result<Self> {
+        let raw = master
+            .as_raw_fd()
+            .ok_or_else(|| anyhow::anyhow!("PTY has no file descriptor"))?;
+        // The duplicate shares the open file, and with it O_NONBLOCK, so
+        // nothing else may read or write the master.
+        let fd = unsafe { libc::fcntl(raw, libc::F_DUPFD_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFL) };
+        if flags < 0
+            || unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0
+        {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(Self {
+            fd: AsyncFd::new(fd)?,
+        })
+    }
+
+    /// Reads what the child wrote. `Ok(0)` once the slave side is closed,
+    /// which Linux reports as EIO.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        // `async_io` counts against the task's budget, so a session that
+        // never stops printing still lets other tasks run.
+        let result = self
+            .fd
+            .async_io(Interest::READABLE, |fd| {
+                let n = unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+                if n < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            })
+            .await;
+        match result {
+            Err(err) if err.raw_os_error() == Some(libc::EIO) => Ok(0),
+            result => result,
+        }
+    }
+
+    /// Waits for as long as the child leaves its input unread.
+    pub async fn write_all(&self, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let written = self
+                .fd
+                .async_io(Interest::WRITABLE, |fd| {
+                    let n =
+                        unsafe { libc::write(fd.as_raw_fd(), data.as_ptr().cast(), data.len()) };
+                    if n >= 0 {
+                        return Ok(n as usize);
+                    }
+                    let err = io::Error::last_os_error();
+                    // Once the slave side is closed, writes keep failing
+                    // with EAGAIN while the fd keeps polling as ready.
+                    if err.kind() == io::ErrorKind::WouldBlock && hung_up(fd) {
+                        return Err(io::Error::from_raw_os_error(libc::EIO));
+                    }
+                    Err(err)
+                })
+                .await;
+            match written {
+                Ok(written) => data = &data[written..],
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn hung_up(fd: &OwnedFd) -> bool {
+    let mut poll = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLOUT,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut poll, 1, 0) > 0 && poll.revents & libc::POLLHUP != 0 }
+}
+
+/// Elsewhere the PTY's pipes are read and written on the runtime's
+/// blocking pool.
+#[cfg(not(unix))]
+pub struct PtyIo {
+    reader: Arc<Mutex<Box<dyn Read + Send>>>,
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+#[cfg(not(unix))]
+impl PtyIo {
+    pub fn new(master: &dyn MasterPty) -> Result<Self> {
+        Ok(Self {
+            reader: Arc::new(Mutex::new(master.try_clone_reader()?)),
+            writer: Arc::new(Mutex::new(master.take_writer()?)),
+        })
+    }
+
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let reader = self.reader.clone();
+        let len = buf.len();
+        let data = tokio::task::spawn_blocking(move || {
+            let mut data = vec![0; len];
+            let mut reader = reader
+                .lock()
+                .map_err(|_| io::Error::other("PTY reader unavailable"))?;
+            let n = reader.read(&mut data)?;
+            data.truncate(n);
+            Ok::<_, io::Error>(data)
+        })
+        .await
+        .map_err(io::Error::other)??;
+        buf[..data.len()].copy_from_slice(&data);
+        Ok(data.len())
+    }
+
+    pub async fn write_all(&self, data: &[u8]) -> io::Result<()> {
+        let writer = self.writer.clone();
+        let data = data.to_vec();
+        tokio::task::spawn_blocking(move || {
+            let mut writer = writer
+                .lock()
+                .map_err(|_| io::Error::other("PTY writer unavailable"))?;
+            writer.write_all(&data)?;
+            writer.flush()
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::Result;
+use serde_json::json;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Frames waiting for the writer thread. A read is at most a few KB, so
+/// this bounds the queue to a few MB.
+const QUEUE_FRAMES: usize = 1024;
+
+enum Frame {
+    Output(f64, Vec<u8>),
+    Input(f64, Vec<u8>),
+    Resize(f64, u16, u16),
+    /// Frames that didn't fit in the queue.
+    Dropped(f64, usize),
+}
+
+/// Tees a session into an asciicast v2 (`.cast`) file.
+///
+/// Producers only timestamp and enqueue frames; encoding and disk I/O happen
+/// on a dedicated writer thread so the PTY read loop never waits on the
+/// file. When the disk falls behind and the queue is full, frames are
+/// dropped, and a marker event saying how many goes into the file in their
+/// place. Dropping the recorder closes the queue, and the writer flushes the
+/// file once it has drained; `finish` waits for that.
+pub struct Recorder {
+    sender: SyncSender<Frame>,
+    writer: JoinHandle<()>,
+    dropped: AtomicUsize,
+    started: Instant,
+    record_input: bool,
+    path: PathBuf,
+}
+
+impl Recorder {
+    pub fn start(path: &Path, cols: u16, rows: u16, record_input: bool) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = File::create(path)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let header = json!({
+            "version": 2,
+            "width": cols,
+            "height": rows,
+            "timestamp": timestamp,
+            "env": {
+                "TERM": "xterm-256color",
+                "SHELL": std::env::var("SHELL").unwrap_or_default(),
+            },
+        });
+        writeln!(writer, "{}", header)?;
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_FRAMES);
+        let thread_path = path.to_path_buf();
+        let writer = std::thread::spawn(move || {
+            if let Err(err) = write_frames(writer, receiver) {
+                eprintln!("Recording to {} failed: {}", thread_path.display(), err);
+            }
+        });
+
+        Ok(Self {
+            sender,
+            writer,
+            dropped: AtomicUsize::new(0),
+            started: Instant::now(),
+            record_input,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stops recording and returns once the file is complete.
+    pub fn finish(self) -> PathBuf {
+        let Self {
+            sender,
+            writer,
+            path,
+            ..
+        } = self;
+        drop(sender);
+        let _ = writer.join();
+        path
+    }
+
+    pub fn output(&self, data: &[u8]) {
+        self.send(Frame::Output(self.elapsed(), data.to_vec()));
+    }
+
+    pub fn input(&self, data: &[u8]) {
+        if self.record_input {
+            self.send(Frame::Input(self.elapsed(), data.to_vec()));
+        }
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) {
+        self.send(Frame::Resize(self.elapsed(), cols, rows));
+    }
+
+    /// Never waits: the callers are the session's I/O tasks.
+    fn send(&self, frame: Frame) {
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        if dropped > 0 {
+            match self
+                .sender
+                .try_send(Frame::Dropped(self.elapsed(), dropped))
+            {
+                Ok(()) => {
+                    self.dropped.fetch_sub(dropped, Ordering::Relaxed);
+                }
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Err(TrySendError::Disconnected(_)) => return,
+            }
+        }
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(frame) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn elapsed(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+}
+
+fn write_frames(mut writer: BufWriter<File>, receiver: Receiver<Frame>) -> Result<()> {
+    let mut output = Utf8Carry::default();
+    let mut input = Utf8Carry::default();
+
+    while let Ok(frame) = receiver.recv() {
+        let (time, code, data) = match frame {
+            Frame::Output(time, bytes) => (time, "o", output.decode(&bytes)),
+            Frame::Input(time, bytes) => (time, "i", input.decode(&bytes)),
+            Frame::Resize(time, cols, rows) => (time, "r", format!("{}x{}", cols, rows)),
+            Frame::Dropped(time, frames) => (
+                time,
+                "m",
+                format!("{} frames dropped: disk too slow", frames),
+            ),
+        };
+
+        if data.is_empty() {
+            continue;
+        }
+        // asciinema rounds to microseconds as well.
+        let time = (time * 1_000_000.0).round() / 1_000_000.0;
+        writeln!(writer, "{}", json!([time, code, data]))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// asciicast frames are JSON strings, so bytes have to be valid UTF-8. PTY
+/// reads can split a multi-byte character; hold the incomplete tail back
+/// until the next chunk completes it. Also used for text transcripts.
+#[derive(Default)]
+pub(super) struct Utf8Carry {
+    pending: Vec<u8>,
+}
+
+impl Utf8Carry {
+    pub(super) fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+
+        let complete = self.pending.len() - incomplete_tail_len(&self.pending);
+        let text = String::from_utf8_lossy(&self.pending[..complete]).into_owned();
+        self.pending.drain(..complete);
+        text
+    }
+}
+
+/// Length of a trailing multi-byte sequence that is still missing bytes.
+fn incomplete_tail_len(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - back];
+        if byte & 0xC0 == 0x80 {
+            // Continuation byte, keep looking for the lead byte.
+            continue;
+        }
+        let needed = match byte {
+            0xF0..=0xFF => 4,
+            0xE0..=0xEF => 3,
+            0xC0..=0xDF => 2,
+            _ => 1,
+        };
+        return if needed > back { back } else { 0 };
+    }
+    0
+}
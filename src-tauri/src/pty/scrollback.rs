@@ -0,0 +1,48 @@
+use std::collections::VecDeque;
+
+/// Default amount of raw output kept per session for replay.
+pub const DEFAULT_SCROLLBACK_BYTES: usize = 512 * 1024;
+
+/// Bounded ring buffer holding the most recent raw PTY output of a session.
+///
+/// The buffer is byte-oriented, so a replay may start in the middle of an
+/// escape sequence or UTF-8 character; xterm.js recovers from both.
+pub struct ScrollbackBuffer {
+    data: VecDeque<u8>,
+    capacity: usize,
+}
+
+impl ScrollbackBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            data: VecDeque::with_capacity(capacity.min(64 * 1024)),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        if chunk.len() >= self.capacity {
+            self.data.clear();
+            self.data.extend(&chunk[chunk.len() - self.capacity..]);
+            return;
+        }
+
+        let overflow = (self.data.len() + chunk.len()).saturating_sub(self.capacity);
+        self.data.drain(..overflow);
+        self.data.extend(chunk);
+    }
+
+    pub fn snapshot(&self) -> Vec<u8> {
+        let (front, back) = self.data.as_slices();
+        let mut out = Vec::with_capacity(self.data.len());
+        out.extend_from_slice(front);
+        out.extend_from_slice(back);
+        out
+    }
+}
+
+impl Default for ScrollbackBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_SCROLLBACK_BYTES)
+    }
+}
@@ -1,53 +1,336 @@
-use super::unix_pty::UnixPty;
+use super::exit::{wait_for_exit, ExitInfo};
+use super::flow::OutputFlow;
+use super::input::InputQueue;
+use super::monitor::ActivityMonitor;
+use super::process::is_local_host;
+use super::pty_io::PtyIo;
+use super::recording::Recorder;
+use super::scrollback::ScrollbackBuffer;
+#[cfg(unix)]
+use super::signal::SignalTarget;
+use super::transcript::TranscriptLogger;
+use super::triggers::TriggerMatcher;
+use super::unix_pty::{CellSize, SpawnOptions, UnixPty};
 use anyhow::Result;
-use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+/// Minimum time between two resizes reaching the child. Resizes arriving
+/// faster, e.g. while a window is dragged, are merged into the last one.
+pub const RESIZE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An OSC 7 report: the directory and the host it is on, which is not
+/// this machine when the shell runs over ssh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportedCwd {
+    pub host: Option<String>,
+    pub path: String,
+}
+
+/// State shared between a session and its output and waiter tasks.
+#[derive(Default)]
+pub struct SessionShared {
+    pub scrollback: Mutex<ScrollbackBuffer>,
+    pub exit_status: Mutex<Option<ExitInfo>>,
+    /// Working directory last reported by the shell through OSC 7.
+    pub reported_cwd: Mutex<Option<ReportedCwd>>,
+    pub recorder: Mutex<Option<Recorder>>,
+    pub transcript: Mutex<Option<TranscriptLogger>>,
+    /// The program asked for pastes to be bracketed (DECSET 2004).
+    pub bracketed_paste: AtomicBool,
+    /// Bytes written to the child and read from it.
+    pub bytes_in: AtomicU64,
+    pub bytes_out: AtomicU64,
+    /// Last output and activity/silence thresholds.
+    pub monitor: Mutex<ActivityMonitor>,
+    /// Patterns watched for in the output; `None` when there are none.
+    pub triggers: Mutex<Option<TriggerMatcher>>,
+    /// Output between the reader and the flusher task.
+    pub flow: OutputFlow,
+}
+
+impl SessionShared {
+    pub fn with_recorder(&self, f: impl FnOnce(&Recorder)) {
+        if let Ok(recorder) = self.recorder.lock() {
+            if let Some(recorder) = recorder.as_ref() {
+                f(recorder);
+            }
+        }
+    }
+
+    pub fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste.load(Ordering::Relaxed)
+    }
+
+    pub fn with_transcript(&self, f: impl FnOnce(&TranscriptLogger)) {
+        if let Ok(transcript) = self.transcript.lock() {
+            if let Some(transcript) = transcript.as_ref() {
+                f(transcript);
+            }
+        }
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.exit_status
+            .lock()
+            .map(|status| status.is_some())
+            .unwrap_or(false)
+    }
+}
+
+/// The latest requested size, which may not have reached the PTY yet.
+struct SizeState {
+    cols: u16,
+    rows: u16,
+    cell: CellSize,
+    last_resize: Option<Instant>,
+    resize_pending: bool,
+}
+
+/// A live session. The manager hands out `Arc`s to it, so everything here
+/// works through `&self` and a slow session never holds up the others.
 pub struct PtySession {
     pub id: Uuid,
     pub pty: UnixPty,
-    pub cols: u16,
-    pub rows: u16,
+    pub created_at: SystemTime,
+    size: Mutex<SizeState>,
+    io: Arc<PtyIo>,
+    pub input: InputQueue,
+    pub shared: Arc<SessionShared>,
 }
 
 impl PtySession {
-    pub fn new(
-        shell: Option<String>,
-        args: Option<Vec<String>>,
-        cwd: Option<PathBuf>,
-        cols: u16,
-        rows: u16,
-    ) -> Result<Self> {
-        let pty = UnixPty::new(shell, args, cwd, cols, rows)?;
+    pub fn new(mut options: SpawnOptions) -> Result<Self> {
+        let id = Uuid::new_v4();
+        let (cols, rows, cell) = (options.cols, options.rows, options.cell);
+        let transcript = options.transcript.take();
+        let triggers = std::mem::take(&mut options.triggers);
+        let pty = UnixPty::new(id, options)?;
+
+        // Attached before the output task starts, so the transcript
+        // begins with the very first prompt. A session is still usable
+        // without its log.
+        let transcript = transcript.and_then(|config| {
+            TranscriptLogger::start(&config, id)
+                .map_err(|err| eprintln!("Transcript logging unavailable: {}", err))
+                .ok()
+        });
+        let triggers = TriggerMatcher::new(triggers).unwrap_or_else(|err| {
+            eprintln!("Ignoring triggers: {}", err);
+            None
+        });
+
+        let shared = Arc::new(SessionShared {
+            transcript: Mutex::new(transcript),
+            triggers: Mutex::new(triggers),
+            ..Default::default()
+        });
+        let io = Arc::new(pty.open_io()?);
+        let input = InputQueue::start(io.clone(), shared.clone());
 
         Ok(Self {
-            id: Uuid::new_v4(),
+            id,
             pty,
-            cols,
-            rows,
+            created_at: SystemTime::now(),
+            size: Mutex::new(SizeState {
+                cols,
+                rows,
+                cell,
+                last_resize: None,
+                resize_pending: false,
+            }),
+            io,
+            input,
+            shared,
         })
     }
 
-    pub fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
-        self.cols = cols;
-        self.rows = rows;
-        self.pty.resize(cols, rows)
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+            .lock()
+            .map(|size| (size.cols, size.rows))
+            .unwrap_or_default()
     }
 
-    pub fn write(&mut self, data: &[u8]) -> Result<usize> {
-        self.pty.write(data)
+    /// Records the new size and applies it, unless another resize was
+    /// applied less than `RESIZE_INTERVAL` ago. Returns true when the size
+    /// was deferred; the caller then calls `flush_resize` once the interval
+    /// has passed. A `None` cell size keeps the previous one.
+    pub fn resize(&self, cols: u16, rows: u16, cell: Option<CellSize>) -> Result<bool> {
+        let mut size = self
+            .size
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Session size unavailable"))?;
+        size.cols = cols;
+        size.rows = rows;
+        if let Some(cell) = cell {
+            size.cell = cell;
+        }
+
+        if size.resize_pending {
+            return Ok(false);
+        }
+        if size
+            .last_resize
+            .is_some_and(|at| at.elapsed() < RESIZE_INTERVAL)
+        {
+            size.resize_pending = true;
+            return Ok(true);
+        }
+        self.apply_resize(&mut size)?;
+        Ok(false)
     }
 
-    pub fn try_clone_reader(&self) -> Result<Box<dyn std::io::Read + Send>> {
-        self.pty.try_clone_reader()
+    /// Applies the size deferred by `resize`, if any.
+    pub fn flush_resize(&self) -> Result<()> {
+        let mut size = self
+            .size
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Session size unavailable"))?;
+        if !size.resize_pending {
+            return Ok(());
+        }
+        size.resize_pending = false;
+        self.apply_resize(&mut size)
     }
 
-    pub fn kill(&mut self) -> Result<()> {
-        let _ = self.pty.child.kill();
+    fn apply_resize(&self, size: &mut SizeState) -> Result<()> {
+        let (cols, rows) = (size.cols, size.rows);
+        size.last_resize = Some(Instant::now());
+        self.shared
+            .with_recorder(|recorder| recorder.resize(cols, rows));
+        self.pty.resize(cols, rows, size.cell)
+    }
+
+    pub fn io(&self) -> Arc<PtyIo> {
+        self.io.clone()
+    }
+
+    pub fn replay(&self) -> Vec<u8> {
+        self.shared
+            .scrollback
+            .lock()
+            .map(|buffer| buffer.snapshot())
+            .unwrap_or_default()
+    }
+
+    /// Starts a task that waits for the child to exit, reaps it and records
+    /// its exit status. Can only be called once per session.
+    pub fn spawn_waiter(&mut self) -> Result<JoinHandle<ExitInfo>> {
+        let child = self
+            .pty
+            .take_child()
+            .ok_or_else(|| anyhow::anyhow!("Session child already taken"))?;
+        let shared = self.shared.clone();
+
+        Ok(tokio::spawn(async move {
+            let info = wait_for_exit(child).await;
+            if let Ok(mut status) = shared.exit_status.lock() {
+                *status = Some(info.clone());
+            }
+            info
+        }))
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.shared.has_exited()
+    }
+
+    #[cfg(unix)]
+    pub fn signal(&self, signal: libc::c_int, target: SignalTarget) -> Result<()> {
+        if self.has_exited() {
+            return Err(anyhow::anyhow!("Session process has already exited"));
+        }
+
+        let result = match target {
+            SignalTarget::Shell => {
+                let pid = self
+                    .get_child_pid()
+                    .ok_or_else(|| anyhow::anyhow!("Session PID unavailable"))?;
+                unsafe { libc::kill(pid as libc::pid_t, signal) }
+            }
+            SignalTarget::Foreground => {
+                let pgid = self
+                    .pty
+                    .foreground_pgid()
+                    .ok_or_else(|| anyhow::anyhow!("Foreground process group unavailable"))?;
+                unsafe { libc::killpg(pgid, signal) }
+            }
+        };
+
+        if result != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Sends SIGHUP, then SIGKILL to the shell and the foreground process
+    /// group if the child is still around once `grace` has elapsed. The
+    /// waiter task takes care of reaping it.
+    pub fn kill(&self, grace: Duration) -> Result<()> {
+        if self.has_exited() {
+            return Ok(());
+        }
+
+        // Look this up first: once the shell is gone the PTY has no
+        // foreground group to report.
+        let foreground = self.pty.foreground_pgid();
+
+        if let Ok(mut killer) = self.pty.killer.lock() {
+            let _ = killer.kill();
+        }
+
+        let deadline = Instant::now() + grace;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            std::thread::sleep(remaining.min(Duration::from_millis(50)));
+            if self.has_exited() {
+                return Ok(());
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            let shell = self.pty.pid.map(|pid| pid as libc::pid_t);
+            // Only while the PTY still reports the group as its foreground:
+            // once the job has exited, its pgid may belong to someone else.
+            // Checked before the shell goes, taking the foreground with it.
+            let still_foreground = foreground
+                .filter(|&pgid| Some(pgid) != shell && self.pty.foreground_pgid() == Some(pgid));
+            if let Some(pid) = shell {
+                unsafe {
+                    libc::kill(pid, libc::SIGKILL);
+                }
+            }
+            if let Some(pgid) = still_foreground {
+                unsafe {
+                    libc::killpg(pgid, libc::SIGKILL);
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        let _ = foreground;
+
         Ok(())
     }
 
     pub fn get_child_pid(&self) -> Option<u32> {
-        self.pty.child.process_id()
+        self.pty.pid
+    }
+
+    /// The reported working directory, if it is on this machine.
+    pub fn reported_cwd(&self) -> Option<String> {
+        let cwd = self.shared.reported_cwd.lock().ok()?.clone()?;
+        cwd.host
+            .as_deref()
+            .is_none_or(is_local_host)
+            .then_some(cwd.path)
     }
 }
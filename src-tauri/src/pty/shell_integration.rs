@@ -0,0 +1,154 @@
+use crate::config::abbyterm_dir;
+use anyhow::Result;
+use portable_pty::CommandBuilder;
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Scripts emitting OSC 133 prompt/command marks and OSC 7 cwd reports,
+/// installed under `~/.abbyterm/shell-integration` (path, contents).
+const SCRIPTS: &[(&str, &str)] = &[
+    (
+        "abbyterm.bash",
+        include_str!("../../shell-integration/abbyterm.bash"),
+    ),
+    (
+        "zsh/.zshenv",
+        include_str!("../../shell-integration/zsh/.zshenv"),
+    ),
+    (
+        "zsh/abbyterm.zsh",
+        include_str!("../../shell-integration/zsh/abbyterm.zsh"),
+    ),
+    (
+        "fish/fish/vendor_conf.d/abbyterm.fish",
+        include_str!("../../shell-integration/fish/fish/vendor_conf.d/abbyterm.fish"),
+    ),
+];
+
+/// Used for `XDG_DATA_DIRS` when it is unset, per the XDG base dir spec.
+const DEFAULT_XDG_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+
+/// `[shell_integration]` section of `~/.abbyterm/config.toml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ShellIntegrationConfig {
+    /// Inject the integration scripts into interactive bash, zsh and fish
+    /// sessions started without explicit arguments.
+    pub enabled: bool,
+}
+
+impl Default for ShellIntegrationConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum IntegrationShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl IntegrationShell {
+    /// `shell_name` is the lowercased file name of the shell binary.
+    pub fn detect(shell_name: &str) -> Option<Self> {
+        match shell_name {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            _ => None,
+        }
+    }
+}
+
+/// Adds the arguments and environment that make `shell` load our script
+/// alongside the user's own startup files. `login` mirrors the `-l` flag the
+/// shell would otherwise have been started with. Must run after the child
+/// environment has been set up, as it reads `ZDOTDIR` and `XDG_DATA_DIRS`.
+pub fn inject(shell: IntegrationShell, command: &mut CommandBuilder, login: bool) -> Result<()> {
+    let dir = install_scripts()?;
+
+    match shell {
+        IntegrationShell::Bash => {
+            // A login bash ignores --init-file, so the script emulates the
+            // login startup sequence instead.
+            if login {
+                command.env("ABBYTERM_SHELL_LOGIN", "1");
+            }
+            command.arg("--init-file");
+            command.arg(dir.join("abbyterm.bash"));
+            command.arg("-i");
+        }
+        IntegrationShell::Zsh => {
+            let user_zdotdir = command
+                .get_env("ZDOTDIR")
+                .map(|dir| dir.to_os_string())
+                .unwrap_or_default();
+            command.env("ABBYTERM_USER_ZDOTDIR", user_zdotdir);
+            command.env("ZDOTDIR", dir.join("zsh"));
+            if login {
+                command.arg("-l");
+            }
+            command.arg("-i");
+        }
+        IntegrationShell::Fish => {
+            let xdg_dir = dir.join("fish");
+            let data_dirs = command
+                .get_env("XDG_DATA_DIRS")
+                .filter(|dirs| !dirs.is_empty())
+                .map(|dirs| dirs.to_os_string())
+                .unwrap_or_else(|| OsString::from(DEFAULT_XDG_DATA_DIRS));
+
+            let mut prefixed = xdg_dir.clone().into_os_string();
+            prefixed.push(":");
+            prefixed.push(data_dirs);
+
+            command.env("XDG_DATA_DIRS", prefixed);
+            command.env("ABBYTERM_SHELL_INTEGRATION_XDG_DIR", xdg_dir);
+            if login {
+                command.arg("-l");
+            }
+            command.arg("-i");
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the bundled scripts to disk, skipping files that are already up
+/// to date, and returns the directory holding them.
+fn install_scripts() -> Result<PathBuf> {
+    let dir = abbyterm_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
+        .join("shell-integration");
+
+    for (relative, contents) in SCRIPTS {
+        let path = dir.join(relative);
+        if fs::read_to_string(&path).ok().as_deref() == Some(*contents) {
+            continue;
+        }
+        write_atomically(&path, contents)?;
+    }
+
+    Ok(dir)
+}
+
+/// Sessions may be spawned concurrently, so never let a shell observe a
+/// half-written script.
+fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Invalid script path {}", path.display()))?;
+    fs::create_dir_all(parent)?;
+
+    let tmp = parent.join(format!(".abbyterm-{}.tmp", Uuid::new_v4()));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        anyhow::anyhow!("Failed to install {}: {}", path.display(), e)
+    })
+}
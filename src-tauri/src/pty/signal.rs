@@ -0,0 +1,85 @@
+#[cfg(unix)]
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Signals we know by name, in the order `kill -l` lists them.
+#[cfg(unix)]
+const SIGNALS: &[(libc::c_int, &str)] = &[
+    (libc::SIGHUP, "SIGHUP"),
+    (libc::SIGINT, "SIGINT"),
+    (libc::SIGQUIT, "SIGQUIT"),
+    (libc::SIGILL, "SIGILL"),
+    (libc::SIGTRAP, "SIGTRAP"),
+    (libc::SIGABRT, "SIGABRT"),
+    (libc::SIGBUS, "SIGBUS"),
+    (libc::SIGFPE, "SIGFPE"),
+    (libc::SIGKILL, "SIGKILL"),
+    (libc::SIGUSR1, "SIGUSR1"),
+    (libc::SIGSEGV, "SIGSEGV"),
+    (libc::SIGUSR2, "SIGUSR2"),
+    (libc::SIGPIPE, "SIGPIPE"),
+    (libc::SIGALRM, "SIGALRM"),
+    (libc::SIGTERM, "SIGTERM"),
+    (libc::SIGCHLD, "SIGCHLD"),
+    (libc::SIGCONT, "SIGCONT"),
+    (libc::SIGSTOP, "SIGSTOP"),
+    (libc::SIGTSTP, "SIGTSTP"),
+    (libc::SIGTTIN, "SIGTTIN"),
+    (libc::SIGTTOU, "SIGTTOU"),
+    (libc::SIGXCPU, "SIGXCPU"),
+    (libc::SIGXFSZ, "SIGXFSZ"),
+    (libc::SIGWINCH, "SIGWINCH"),
+    (libc::SIGSYS, "SIGSYS"),
+];
+
+/// Returns the conventional name (`SIGSEGV`) for a signal number.
+#[cfg(unix)]
+pub fn signal_name(signal: libc::c_int) -> String {
+    SIGNALS
+        .iter()
+        .find(|(number, _)| *number == signal)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("SIG{}", signal))
+}
+
+/// Signals the frontend may deliver through `pty_signal`.
+#[cfg(unix)]
+const DELIVERABLE_SIGNALS: &[libc::c_int] = &[
+    libc::SIGINT,
+    libc::SIGTERM,
+    libc::SIGHUP,
+    libc::SIGQUIT,
+    libc::SIGSTOP,
+    libc::SIGCONT,
+    libc::SIGUSR1,
+];
+
+/// Which process(es) of a session a signal is delivered to.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalTarget {
+    /// The shell (or command) the session was spawned with.
+    Shell,
+    /// Every process in the PTY's foreground process group.
+    #[default]
+    Foreground,
+}
+
+/// Parses `SIGINT`, `INT` or `int` into a signal number, rejecting anything
+/// outside the deliverable set.
+#[cfg(unix)]
+pub fn parse_signal(name: &str) -> Result<libc::c_int> {
+    let upper = name.trim().to_ascii_uppercase();
+    let full = if upper.starts_with("SIG") {
+        upper
+    } else {
+        format!("SIG{}", upper)
+    };
+
+    SIGNALS
+        .iter()
+        .find(|(_, known)| *known == full)
+        .map(|(number, _)| *number)
+        .filter(|number| DELIVERABLE_SIGNALS.contains(number))
+        .ok_or_else(|| anyhow::anyhow!("Unsupported signal: {}", name))
+}
@@ -0,0 +1,400 @@
+use super::recording::Utf8Carry;
+use crate::config::abbyterm_dir;
+use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use uuid::Uuid;
+
+/// Lines longer than this are broken up, so output without newlines can't
+/// grow the line buffer without bound.
+const MAX_LINE_CHARS: usize = 16 * 1024;
+
+/// Chunks waiting for the writer thread, a few MB at most.
+const QUEUE_CHUNKS: usize = 1024;
+
+enum Chunk {
+    Output(Vec<u8>),
+    /// Chunks that didn't fit in the queue.
+    Dropped(usize),
+}
+
+/// `[logging]` section of `~/.abbyterm/config.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Log local shell tabs.
+    pub local: bool,
+    /// Log SSH tabs.
+    pub ssh: bool,
+    /// Where transcripts are written. Defaults to `~/.abbyterm/logs`.
+    pub directory: Option<String>,
+    /// Prefix every line with the local time it was completed at.
+    pub timestamps: bool,
+    /// Size in bytes after which a transcript is rotated.
+    pub max_file_size: u64,
+    /// Rotated files kept per session, besides the active one.
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            local: false,
+            ssh: false,
+            directory: None,
+            timestamps: true,
+            max_file_size: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// The tab types that can be logged independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionKind {
+    Local,
+    Ssh,
+}
+
+impl SessionKind {
+    /// SSH tabs run the `ssh` binary directly as the session's shell.
+    pub fn of_shell(shell: Option<&str>) -> Self {
+        let name = shell
+            .and_then(|shell| Path::new(shell).file_stem())
+            .and_then(|name| name.to_str());
+        match name {
+            Some("ssh") => Self::Ssh,
+            _ => Self::Local,
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn enabled_for(&self, kind: SessionKind) -> bool {
+        match kind {
+            SessionKind::Local => self.local,
+            SessionKind::Ssh => self.ssh,
+        }
+    }
+
+    fn log_dir(&self) -> Option<PathBuf> {
+        match &self.directory {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => abbyterm_dir().map(|dir| dir.join("logs")),
+        }
+    }
+}
+
+/// Writes a session's output to a plain-text transcript.
+///
+/// Like [`super::recording::Recorder`], producers only enqueue chunks; escape
+/// sequence stripping, timestamping, rotation and disk I/O happen on a
+/// dedicated thread. Output that doesn't fit in the queue is dropped, and
+/// a line in the transcript says so. Dropping the logger flushes any
+/// partial last line.
+pub struct TranscriptLogger {
+    sender: SyncSender<Chunk>,
+    dropped: AtomicUsize,
+    path: PathBuf,
+}
+
+impl TranscriptLogger {
+    /// Creates `<log dir>/<YYYYMMDD-HHMMSS>-<session>.log`.
+    pub fn start(config: &LoggingConfig, session_id: Uuid) -> Result<Self> {
+        let dir = config
+            .log_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        fs::create_dir_all(&dir)?;
+
+        let session = session_id.to_string();
+        let name = format!(
+            "{}-{}.log",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            &session[..8.min(session.len())]
+        );
+        let path = dir.join(name);
+        let file = open_append(&path)?;
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_CHUNKS);
+        let mut writer = TranscriptWriter {
+            file: BufWriter::new(file),
+            written: 0,
+            path: path.clone(),
+            timestamps: config.timestamps,
+            max_file_size: config.max_file_size,
+            max_files: config.max_files,
+        };
+        std::thread::spawn(move || {
+            if let Err(err) = writer.run(receiver) {
+                eprintln!("Transcript {} failed: {}", writer.path.display(), err);
+            }
+        });
+
+        Ok(Self {
+            sender,
+            dropped: AtomicUsize::new(0),
+            path,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Never waits: the caller is the session's output task.
+    pub fn output(&self, data: &[u8]) {
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        if dropped > 0 {
+            match self.sender.try_send(Chunk::Dropped(dropped)) {
+                Ok(()) => {
+                    self.dropped.fetch_sub(dropped, Ordering::Relaxed);
+                }
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Err(TrySendError::Disconnected(_)) => return,
+            }
+        }
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(Chunk::Output(data.to_vec())) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))
+}
+
+struct TranscriptWriter {
+    file: BufWriter<File>,
+    written: u64,
+    path: PathBuf,
+    timestamps: bool,
+    max_file_size: u64,
+    max_files: usize,
+}
+
+impl TranscriptWriter {
+    fn run(&mut self, receiver: Receiver<Chunk>) -> Result<()> {
+        let mut text = TextExtractor::default();
+        let mut lines = Vec::new();
+
+        while let Ok(chunk) = receiver.recv() {
+            match chunk {
+                Chunk::Output(data) => text.feed(&data, &mut lines),
+                Chunk::Dropped(chunks) => {
+                    lines.extend(text.finish());
+                    lines.push(format!(
+                        "[{} chunks of output dropped: disk too slow]",
+                        chunks
+                    ));
+                }
+            }
+            for line in lines.drain(..) {
+                self.write_line(&line)?;
+            }
+            // Keep the file readable with `tail -f` between bursts.
+            self.file.flush()?;
+        }
+
+        if let Some(line) = text.finish() {
+            self.write_line(&line)?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        let entry = if self.timestamps {
+            format!("[{}] {}\n", Local::now().format("%Y-%m-%d %H:%M:%S"), line)
+        } else {
+            format!("{}\n", line)
+        };
+
+        if self.written > 0 && self.written + entry.len() as u64 > self.max_file_size {
+            self.rotate()?;
+        }
+
+        self.file.write_all(entry.as_bytes())?;
+        self.written += entry.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts `name.log` to `name.1.log`, `name.1.log` to `name.2.log` and so
+    /// on, dropping whatever falls past `max_files`.
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+
+        let rotated = |index: usize| self.path.with_extension(format!("{}.log", index));
+        if self.max_files == 0 {
+            let _ = fs::remove_file(&self.path);
+        } else {
+            let _ = fs::remove_file(rotated(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let _ = fs::rename(rotated(index), rotated(index + 1));
+            }
+            fs::rename(&self.path, rotated(1))?;
+        }
+
+        self.file = BufWriter::new(open_append(&self.path)?);
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// `data` as a reader would have seen it on screen, by the same rules as
+/// transcripts.
+pub fn plain_text(data: &[u8]) -> String {
+    let mut text = TextExtractor::default();
+    let mut lines = Vec::new();
+    text.feed(data, &mut lines);
+    lines.extend(text.finish());
+    lines.join("\n")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    /// ESC followed by intermediate bytes, e.g. a charset designation.
+    EscapeIntermediate,
+    Csi,
+    /// OSC, DCS, SOS, PM or APC payload, terminated by BEL or ST.
+    String,
+    StringEscape,
+}
+
+/// Turns terminal output into the text a reader would have seen, line by
+/// line. Escape sequences are dropped; carriage returns, backspaces and
+/// erase-in-line are applied so progress bars and line editing collapse to
+/// their final state.
+struct TextExtractor {
+    utf8: Utf8Carry,
+    state: State,
+    csi_params: String,
+    line: Vec<char>,
+    cursor: usize,
+}
+
+impl Default for TextExtractor {
+    fn default() -> Self {
+        Self {
+            utf8: Utf8Carry::default(),
+            state: State::Ground,
+            csi_params: String::new(),
+            line: Vec::new(),
+            cursor: 0,
+        }
+    }
+}
+
+impl TextExtractor {
+    fn feed(&mut self, data: &[u8], lines: &mut Vec<String>) {
+        let text = self.utf8.decode(data);
+        for c in text.chars() {
+            self.advance(c, lines);
+        }
+    }
+
+    /// Returns the unterminated last line, if any.
+    fn finish(&mut self) -> Option<String> {
+        let line = self.take_line();
+        (!line.is_empty()).then_some(line)
+    }
+
+    fn advance(&mut self, c: char, lines: &mut Vec<String>) {
+        match self.state {
+            State::Ground => self.ground(c, lines),
+            State::Escape => match c {
+                '[' => {
+                    self.csi_params.clear();
+                    self.state = State::Csi;
+                }
+                ']' | 'P' | 'X' | '^' | '_' => self.state = State::String,
+                '\x1b' => {}
+                ' '..='/' => self.state = State::EscapeIntermediate,
+                _ => self.state = State::Ground,
+            },
+            State::EscapeIntermediate => {
+                if !(' '..='/').contains(&c) {
+                    self.state = State::Ground;
+                }
+            }
+            State::Csi => match c {
+                '0'..='?' => self.csi_params.push(c),
+                ' '..='/' => {}
+                '@'..='~' => {
+                    self.state = State::Ground;
+                    if c == 'K' {
+                        self.erase_in_line();
+                    }
+                }
+                // C0 controls inside a CSI are executed by terminals but
+                // carry no text.
+                _ => {}
+            },
+            State::String => match c {
+                '\x07' => self.state = State::Ground,
+                '\x1b' => self.state = State::StringEscape,
+                _ => {}
+            },
+            State::StringEscape => {
+                self.state = State::Escape;
+                if c == '\\' {
+                    self.state = State::Ground;
+                } else {
+                    self.advance(c, lines);
+                }
+            }
+        }
+    }
+
+    fn ground(&mut self, c: char, lines: &mut Vec<String>) {
+        match c {
+            '\n' => lines.push(self.take_line()),
+            '\r' => self.cursor = 0,
+            '\x08' => self.cursor = self.cursor.saturating_sub(1),
+            '\x1b' => self.state = State::Escape,
+            '\t' => self.put(c, lines),
+            c if c.is_control() => {}
+            c => self.put(c, lines),
+        }
+    }
+
+    fn put(&mut self, c: char, lines: &mut Vec<String>) {
+        if self.cursor < self.line.len() {
+            self.line[self.cursor] = c;
+        } else {
+            self.line.push(c);
+        }
+        self.cursor += 1;
+
+        if self.line.len() >= MAX_LINE_CHARS {
+            lines.push(self.take_line());
+        }
+    }
+
+    fn erase_in_line(&mut self) {
+        match self.csi_params.as_str() {
+            "" | "0" => self.line.truncate(self.cursor),
+            "2" => self.line.clear(),
+            _ => {}
+        }
+    }
+
+    fn take_line(&mut self) -> String {
+        self.cursor = 0;
+        let line: String = self.line.drain(..).collect();
+        line.trim_end().to_string()
+    }
+}
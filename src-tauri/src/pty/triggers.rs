@@ -0,0 +1,196 @@
+use anyhow::Result;
+use regex::bytes::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Lines longer than this are matched in pieces, so output without line
+/// breaks can't make every chunk rescan an ever longer line.
+const MAX_LINE_BYTES: usize = 4096;
+
+/// Minimum time between two automatic responses of the same trigger, so a
+/// response that echoes its own pattern can't loop.
+const RESPOND_COOLDOWN: Duration = Duration::from_secs(1);
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// A pattern watched for in a session's output, from a `[[triggers]]`
+/// entry in `~/.abbyterm/config.toml` or `set_session_triggers`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerRule {
+    /// Regular expression, matched against each line of output with escape
+    /// sequences removed.
+    pub pattern: String,
+    /// Written to the session when the pattern matches. Any output can
+    /// match, including a remote host's, so whoever controls that output
+    /// decides when this is typed.
+    #[serde(default)]
+    pub respond: Option<String>,
+}
+
+/// A trigger that matched. `line` is the line as far as it had been
+/// printed when the pattern first matched it.
+pub struct TriggerHit {
+    pub pattern: String,
+    pub line: String,
+    pub matched: String,
+    pub respond: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    Csi,
+    /// OSC, DCS, SOS, PM or APC payload, terminated by BEL or ST.
+    String,
+    StringEscape,
+}
+
+/// Matches a session's output against its triggers, line by line.
+///
+/// Bytes are collected into the current line across reads, so a pattern is
+/// found no matter where the reads split it. Each line is checked with one
+/// `RegexSet` pass; individual patterns only run again to extract the
+/// match of a trigger that hit. The unfinished last line is checked after
+/// every read, so prompts like `password:` fire before a newline arrives,
+/// and a trigger fires at most once per line.
+pub struct TriggerMatcher {
+    rules: Vec<TriggerRule>,
+    set: RegexSet,
+    regexes: Vec<Regex>,
+    state: State,
+    line: Vec<u8>,
+    /// Length of `line` when it was last checked.
+    checked_len: usize,
+    /// Triggers that already fired on the current line.
+    fired: Vec<bool>,
+    last_response: Vec<Option<Instant>>,
+}
+
+impl TriggerMatcher {
+    /// Compiles the rules; `None` when there are none, so sessions without
+    /// triggers skip matching entirely.
+    pub fn new(rules: Vec<TriggerRule>) -> Result<Option<Self>> {
+        if rules.is_empty() {
+            return Ok(None);
+        }
+
+        let regexes = rules
+            .iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern).map_err(|e| {
+                    anyhow::anyhow!("Invalid trigger pattern {:?}: {}", rule.pattern, e)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let set = RegexSet::new(rules.iter().map(|rule| &rule.pattern))?;
+        let count = rules.len();
+
+        Ok(Some(Self {
+            rules,
+            set,
+            regexes,
+            state: State::Ground,
+            line: Vec::new(),
+            checked_len: 0,
+            fired: vec![false; count],
+            last_response: vec![None; count],
+        }))
+    }
+
+    pub fn feed(&mut self, data: &[u8], hits: &mut Vec<TriggerHit>) {
+        for &byte in data {
+            match self.state {
+                State::Ground => match byte {
+                    b'\n' | b'\r' => self.end_line(hits),
+                    ESC => self.state = State::Escape,
+                    b'\t' => self.line.push(byte),
+                    0x00..=0x1f | 0x7f => {}
+                    _ => {
+                        self.line.push(byte);
+                        if self.line.len() >= MAX_LINE_BYTES {
+                            self.end_line(hits);
+                        }
+                    }
+                },
+                State::Escape => {
+                    self.state = match byte {
+                        b'[' => State::Csi,
+                        b']' | b'P' | b'X' | b'^' | b'_' => State::String,
+                        ESC => State::Escape,
+                        // Intermediates of e.g. a charset designation are
+                        // skipped along with their final byte.
+                        0x20..=0x2f => State::Escape,
+                        _ => State::Ground,
+                    }
+                }
+                State::Csi => match byte {
+                    0x40..=0x7e => self.state = State::Ground,
+                    ESC => self.state = State::Escape,
+                    _ => {}
+                },
+                State::String => match byte {
+                    BEL => self.state = State::Ground,
+                    ESC => self.state = State::StringEscape,
+                    _ => {}
+                },
+                State::StringEscape => {
+                    self.state = match byte {
+                        b'\\' => State::Ground,
+                        b'[' => State::Csi,
+                        _ => State::Ground,
+                    }
+                }
+            }
+        }
+
+        // The rest of the line may never come, e.g. at a prompt.
+        if self.line.len() > self.checked_len {
+            self.check_line(hits);
+        }
+    }
+
+    fn end_line(&mut self, hits: &mut Vec<TriggerHit>) {
+        if self.line.len() > self.checked_len {
+            self.check_line(hits);
+        }
+        self.line.clear();
+        self.checked_len = 0;
+        self.fired.fill(false);
+    }
+
+    fn check_line(&mut self, hits: &mut Vec<TriggerHit>) {
+        self.checked_len = self.line.len();
+        if !self.set.is_match(&self.line) {
+            return;
+        }
+
+        for index in self.set.matches(&self.line).iter() {
+            if self.fired[index] {
+                continue;
+            }
+            self.fired[index] = true;
+
+            let Some(found) = self.regexes[index].find(&self.line) else {
+                continue;
+            };
+            let rule = &self.rules[index];
+            let respond = rule.respond.clone().filter(|_| {
+                let now = Instant::now();
+                let ready = self.last_response[index]
+                    .is_none_or(|last| now.duration_since(last) >= RESPOND_COOLDOWN);
+                if ready {
+                    self.last_response[index] = Some(now);
+                }
+                ready
+            });
+            hits.push(TriggerHit {
+                pattern: rule.pattern.clone(),
+                line: String::from_utf8_lossy(&self.line).into_owned(),
+                matched: String::from_utf8_lossy(found.as_bytes()).into_owned(),
+                respond,
+            });
+        }
+    }
+}
@@ -1,35 +1,87 @@
+use super::env::{sanitized_env, EnvironmentConfig};
+use super::pty_io::PtyIo;
+use super::shell_integration::{self, IntegrationShell};
+use super::transcript::LoggingConfig;
+use super::triggers::TriggerRule;
 use anyhow::Result;
-use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
-use std::io::Write;
+use portable_pty::{native_pty_system, Child, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Size of one character cell in pixels; zero when unknown. Programs that
+/// draw images read the resulting pixel size of the window through
+/// `TIOCGWINSZ`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellSize {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl CellSize {
+    fn pty_size(self, cols: u16, rows: u16) -> PtySize {
+        PtySize {
+            rows,
+            cols,
+            pixel_width: cols.saturating_mul(self.width),
+            pixel_height: rows.saturating_mul(self.height),
+        }
+    }
+}
+
+/// Everything needed to spawn a session's child process.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SpawnOptions {
+    pub shell: Option<String>,
+    pub args: Option<Vec<String>>,
+    pub cwd: Option<PathBuf>,
+    pub cols: u16,
+    pub rows: u16,
+    pub cell: CellSize,
+    /// Extra variables for the child, applied over the defaults.
+    pub env: HashMap<String, String>,
+    /// Variables removed from the child environment after `env` is applied.
+    pub unset_env: Vec<String>,
+    /// How the inherited environment is sanitized before `env` is applied.
+    pub env_filter: EnvironmentConfig,
+    /// Load the OSC 133 / OSC 7 integration script into bash, zsh or fish
+    /// when no explicit `args` are given.
+    pub shell_integration: bool,
+    /// Start a plain-text transcript with these settings as soon as the
+    /// session exists.
+    pub transcript: Option<LoggingConfig>,
+    /// Output triggers the session starts with.
+    #[serde(default)]
+    pub triggers: Vec<TriggerRule>,
+}
 
+/// The PTY pieces are only `Send`; the mutexes let a session be shared.
 pub struct UnixPty {
-    pub master: Box<dyn MasterPty + Send>,
-    pub writer: Box<dyn Write + Send>,
-    pub child: Box<dyn portable_pty::Child + Send + Sync>,
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    pub killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+    pub pid: Option<u32>,
+    /// The program and arguments the child was started with.
+    pub argv: Vec<String>,
+    /// Handed off to the session's waiter thread, which reaps it.
+    child: Option<Box<dyn Child + Send + Sync>>,
 }
 
 impl UnixPty {
-    pub fn new(
-        shell: Option<String>,
-        args: Option<Vec<String>>,
-        cwd: Option<PathBuf>,
-        cols: u16,
-        rows: u16,
-    ) -> Result<Self> {
+    pub fn new(session_id: Uuid, options: SpawnOptions) -> Result<Self> {
         let pty_system = native_pty_system();
-        let pair = pty_system.openpty(PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        })?;
+        let pair = pty_system.openpty(options.cell.pty_size(options.cols, options.rows))?;
 
-        let shell_path = choose_shell(shell);
+        let shell_path = choose_shell(options.shell);
         let mut command = CommandBuilder::new(shell_path.clone());
 
-        if let Some(args) = args {
+        // Injecting shell integration replaces the default arguments and has
+        // to see the final environment, so it is applied further down.
+        let mut integration = None;
+
+        if let Some(args) = options.args {
             command.args(args);
         } else {
             #[cfg(not(target_os = "windows"))]
@@ -44,10 +96,16 @@ impl UnixPty {
                     "bash" | "zsh" | "fish" | "ksh" | "mksh"
                 );
 
-                if supports_login_flag {
-                    command.arg("-l");
+                integration = IntegrationShell::detect(&shell_name)
+                    .filter(|_| options.shell_integration)
+                    .map(|shell| (shell, supports_login_flag));
+
+                if integration.is_none() {
+                    if supports_login_flag {
+                        command.arg("-l");
+                    }
+                    command.arg("-i");
                 }
-                command.arg("-i");
             }
 
             #[cfg(target_os = "windows")]
@@ -66,39 +124,86 @@ impl UnixPty {
             }
         }
 
+        command.env_clear();
+        for (key, value) in sanitized_env(&options.env_filter) {
+            command.env(key, value);
+        }
+
         command.env("TERM", "xterm-256color");
+        command.env("COLORTERM", "truecolor");
+        command.env("TERM_PROGRAM", "AbbyTerm");
+        command.env("TERM_PROGRAM_VERSION", env!("CARGO_PKG_VERSION"));
+
+        for (key, value) in &options.env {
+            command.env(key, value);
+        }
+        for key in &options.unset_env {
+            command.env_remove(key);
+        }
+
+        // Always ours, so tools can address the pane they are running in.
+        command.env("ABBYTERM_SESSION_ID", session_id.to_string());
+
+        if let Some((shell, login)) = integration {
+            if let Err(err) = shell_integration::inject(shell, &mut command, login) {
+                eprintln!("Shell integration unavailable: {}", err);
+                if login {
+                    command.arg("-l");
+                }
+                command.arg("-i");
+            }
+        }
 
-        if let Some(dir) = cwd {
+        if let Some(dir) = options.cwd {
             command.cwd(dir);
         }
 
+        let argv = command
+            .get_argv()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
         let child = pair.slave.spawn_command(command)?;
-        let writer = pair.master.take_writer()?;
 
         Ok(Self {
-            master: pair.master,
-            writer,
-            child,
+            master: Mutex::new(pair.master),
+            killer: Mutex::new(child.clone_killer()),
+            pid: child.process_id(),
+            argv,
+            child: Some(child),
         })
     }
 
-    pub fn resize(&self, cols: u16, rows: u16) -> Result<()> {
-        self.master.resize(PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
+    pub fn take_child(&mut self) -> Option<Box<dyn Child + Send + Sync>> {
+        self.child.take()
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16, cell: CellSize) -> Result<()> {
+        self.master()?.resize(cell.pty_size(cols, rows))
+    }
+
+    /// The master for the session's output and input tasks. Can only be
+    /// opened once per session.
+    pub fn open_io(&self) -> Result<PtyIo> {
+        PtyIo::new(self.master()?.as_ref())
+    }
+
+    fn master(&self) -> Result<std::sync::MutexGuard<'_, Box<dyn MasterPty + Send>>> {
+        self.master
+            .lock()
+            .map_err(|_| anyhow::anyhow!("PTY unavailable"))
     }
 
-    pub fn write(&mut self, data: &[u8]) -> Result<usize> {
-        self.writer.write_all(data)?;
-        self.writer.flush()?;
-        Ok(data.len())
+    /// Process group currently in the foreground of the PTY (`tcgetpgrp` on
+    /// the master), which is the shell itself while it sits at a prompt.
+    #[cfg(unix)]
+    pub fn foreground_pgid(&self) -> Option<i32> {
+        self.master().ok()?.process_group_leader()
     }
 
-    pub fn try_clone_reader(&self) -> Result<Box<dyn std::io::Read + Send>> {
-        self.master.try_clone_reader()
+    #[cfg(not(unix))]
+    pub fn foreground_pgid(&self) -> Option<i32> {
+        None
     }
 }
 
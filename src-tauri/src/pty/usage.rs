@@ -0,0 +1,381 @@
+use super::events::EventSink;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// How many of the heaviest processes a usage report lists.
+const TOP_PROCESSES: usize = 5;
+
+/// Shortest interval a subscriber can ask for.
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// CPU and memory of a session's whole process tree, as reported by
+/// `get_session_resource_usage` and the `session-resource-usage` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub session_id: String,
+    /// CPU time used since the previous sample, in percent of one core.
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub threads: u64,
+    pub processes: usize,
+    /// The busiest processes of the tree, by CPU and then by memory.
+    pub top: Vec<ProcessUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessUsage {
+    pub pid: i32,
+    pub name: String,
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub threads: u64,
+}
+
+/// One reading of every process below a session's shell. CPU usage is the
+/// difference between two of them.
+pub struct TreeSample {
+    taken_at: Instant,
+    processes: HashMap<i32, ProcessSample>,
+}
+
+#[derive(Clone)]
+struct ProcessSample {
+    name: String,
+    /// utime + stime, in clock ticks.
+    cpu_ticks: u64,
+    rss_bytes: u64,
+    threads: u64,
+}
+
+impl TreeSample {
+    /// Reads the process rooted at `root` and all of its descendants.
+    pub fn take(root: i32) -> Result<Self> {
+        ProcessTable::read()?
+            .sample(root)
+            .ok_or_else(|| anyhow::anyhow!("Process {} not found", root))
+    }
+
+    /// Usage over the time since `previous`. Processes that did not exist
+    /// back then are charged for their whole CPU time.
+    pub fn usage_since(&self, previous: &TreeSample, session_id: String) -> ResourceUsage {
+        let elapsed = self
+            .taken_at
+            .saturating_duration_since(previous.taken_at)
+            .max(Duration::from_millis(1));
+        let ticks_per_sec = clock_ticks_per_sec();
+
+        let mut top: Vec<ProcessUsage> = self
+            .processes
+            .iter()
+            .map(|(&pid, process)| {
+                let before = previous
+                    .processes
+                    .get(&pid)
+                    .map(|process| process.cpu_ticks)
+                    .unwrap_or(0);
+                let ticks = process.cpu_ticks.saturating_sub(before);
+                ProcessUsage {
+                    pid,
+                    name: process.name.clone(),
+                    cpu_percent: ticks as f64 / ticks_per_sec / elapsed.as_secs_f64() * 100.0,
+                    rss_bytes: process.rss_bytes,
+                    threads: process.threads,
+                }
+            })
+            .collect();
+
+        let cpu_percent = top.iter().map(|process| process.cpu_percent).sum();
+        let rss_bytes = top.iter().map(|process| process.rss_bytes).sum();
+        let threads = top.iter().map(|process| process.threads).sum();
+        let processes = top.len();
+
+        top.sort_by(|a, b| {
+            b.cpu_percent
+                .total_cmp(&a.cpu_percent)
+                .then(b.rss_bytes.cmp(&a.rss_bytes))
+        });
+        top.truncate(TOP_PROCESSES);
+
+        ResourceUsage {
+            session_id,
+            cpu_percent,
+            rss_bytes,
+            threads,
+            processes,
+            top,
+        }
+    }
+}
+
+/// Samples the tree twice, `window` apart, for a one-off reading.
+pub fn measure(root: i32, window: Duration, session_id: String) -> Result<ResourceUsage> {
+    let first = TreeSample::take(root)?;
+    std::thread::sleep(window);
+    Ok(TreeSample::take(root)?.usage_since(&first, session_id))
+}
+
+/// Every process on the system, read in one pass over /proc. Sessions
+/// watched at the same time share one of these.
+pub struct ProcessTable {
+    taken_at: Instant,
+    processes: HashMap<i32, ProcessSample>,
+    children: HashMap<i32, Vec<i32>>,
+}
+
+impl ProcessTable {
+    #[cfg(target_os = "linux")]
+    pub fn read() -> Result<Self> {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64;
+
+        let taken_at = Instant::now();
+        let mut processes = HashMap::new();
+        let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+        for entry in std::fs::read_dir("/proc")?.flatten() {
+            let Some(pid) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse().ok())
+            else {
+                continue;
+            };
+            // Processes may exit while we walk /proc.
+            let Some((ppid, sample)) = read_stat(pid, page_size) else {
+                continue;
+            };
+            children.entry(ppid).or_default().push(pid);
+            processes.insert(pid, sample);
+        }
+
+        Ok(Self {
+            taken_at,
+            processes,
+            children,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn read() -> Result<Self> {
+        Err(anyhow::anyhow!(
+            "Resource usage is not supported on this platform"
+        ))
+    }
+
+    /// The process rooted at `root` and all of its descendants, or `None`
+    /// once `root` has exited.
+    pub fn sample(&self, root: i32) -> Option<TreeSample> {
+        self.processes.get(&root)?;
+
+        let mut processes = HashMap::new();
+        let mut pending = vec![root];
+        while let Some(pid) = pending.pop() {
+            if let Some(sample) = self.processes.get(&pid) {
+                if processes.insert(pid, sample.clone()).is_none() {
+                    pending.extend(self.children.get(&pid).into_iter().flatten());
+                }
+            }
+        }
+        Some(TreeSample {
+            taken_at: self.taken_at,
+            processes,
+        })
+    }
+}
+
+/// Parses `/proc/<pid>/stat` into the parent pid and a sample.
+#[cfg(target_os = "linux")]
+fn read_stat(pid: i32, page_size: u64) -> Option<(i32, ProcessSample)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+
+    // As in `get_process_start_time_ms`, fields are counted after the
+    // parenthesised command name: ppid is field 4 overall, utime 14,
+    // stime 15, num_threads 20 and rss 24.
+    let (head, rest) = stat.rsplit_once(')')?;
+    let name = head.split_once('(')?.1.to_string();
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let field = |index: usize| -> Option<u64> { fields.get(index)?.parse().ok() };
+
+    let ppid = fields.get(1)?.parse().ok()?;
+    Some((
+        ppid,
+        ProcessSample {
+            name,
+            cpu_ticks: field(11)? + field(12)?,
+            threads: field(17)?,
+            rss_bytes: field(21)? * page_size,
+        },
+    ))
+}
+
+fn clock_ticks_per_sec() -> f64 {
+    #[cfg(unix)]
+    {
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks > 0 {
+            return ticks as f64;
+        }
+    }
+    100.0
+}
+
+struct Subscriber {
+    pid: i32,
+    interval: Duration,
+    next: Instant,
+    previous: TreeSample,
+    events: EventSink,
+}
+
+#[derive(Default)]
+struct Subscribers {
+    by_session: HashMap<Uuid, Subscriber>,
+    /// Whether the sampling task is running; it stops once nobody is
+    /// subscribed.
+    sampling: bool,
+}
+
+/// Sessions getting `session-resource-usage` events. One task reads /proc
+/// for all of them: subscribers tick on a shared grid, so those with the
+/// same interval share each reading. A subscriber whose shell is gone is
+/// dropped.
+pub struct UsageSubscriptions {
+    subscribers: Arc<Mutex<Subscribers>>,
+    changed: Arc<Notify>,
+    epoch: Instant,
+}
+
+impl UsageSubscriptions {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Arc::default(),
+            changed: Arc::default(),
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Emits usage of the tree rooted at `pid` every `interval`, starting
+    /// from `first`. Replaces an earlier subscription of the session.
+    pub fn subscribe(
+        &self,
+        id: Uuid,
+        pid: i32,
+        interval: Duration,
+        first: TreeSample,
+        events: EventSink,
+    ) {
+        let interval = interval.max(MIN_INTERVAL);
+        let Ok(mut subscribers) = self.subscribers.lock() else {
+            return;
+        };
+        subscribers.by_session.insert(
+            id,
+            Subscriber {
+                pid,
+                interval,
+                next: next_tick(self.epoch, interval, Instant::now()),
+                previous: first,
+                events,
+            },
+        );
+        if !subscribers.sampling {
+            subscribers.sampling = true;
+            let subscribers = self.subscribers.clone();
+            let changed = self.changed.clone();
+            tokio::spawn(sample_subscribers(subscribers, changed, self.epoch));
+        }
+        drop(subscribers);
+        self.changed.notify_waiters();
+    }
+
+    pub fn unsubscribe(&self, id: Uuid) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.by_session.remove(&id);
+        }
+        self.changed.notify_waiters();
+    }
+}
+
+impl Default for UsageSubscriptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn sample_subscribers(
+    subscribers: Arc<Mutex<Subscribers>>,
+    changed: Arc<Notify>,
+    epoch: Instant,
+) {
+    loop {
+        let notified = changed.notified();
+        tokio::pin!(notified);
+        // Enabled first, so a subscription made while we look wakes us.
+        notified.as_mut().enable();
+        let next = {
+            let Ok(mut subscribers) = subscribers.lock() else {
+                return;
+            };
+            match subscribers
+                .by_session
+                .values()
+                .map(|subscriber| subscriber.next)
+                .min()
+            {
+                Some(next) => next,
+                None => {
+                    subscribers.sampling = false;
+                    return;
+                }
+            }
+        };
+        if next > Instant::now() {
+            tokio::select! {
+                _ = tokio::time::sleep_until(next.into()) => {}
+                _ = notified => continue,
+            }
+        }
+
+        let Ok(Ok(table)) = tokio::task::spawn_blocking(ProcessTable::read).await else {
+            // /proc couldn't be read; try again shortly.
+            tokio::time::sleep(MIN_INTERVAL).await;
+            continue;
+        };
+
+        let mut reports = Vec::new();
+        let Ok(mut subscribers) = subscribers.lock() else {
+            return;
+        };
+        subscribers.by_session.retain(|&id, subscriber| {
+            if subscriber.next > table.taken_at {
+                return true;
+            }
+            // The shell is gone.
+            let Some(sample) = table.sample(subscriber.pid) else {
+                return false;
+            };
+            reports.push((
+                subscriber.events.clone(),
+                sample.usage_since(&subscriber.previous, id.to_string()),
+            ));
+            subscriber.previous = sample;
+            subscriber.next = next_tick(epoch, subscriber.interval, table.taken_at);
+            true
+        });
+        drop(subscribers);
+
+        for (events, usage) in reports {
+            events.emit("session-resource-usage", usage);
+        }
+    }
+}
+
+/// The first multiple of `interval` after `epoch` that is later than `now`.
+fn next_tick(epoch: Instant, interval: Duration, now: Instant) -> Instant {
+    let elapsed = now.saturating_duration_since(epoch).as_nanos();
+    let ticks = elapsed / interval.as_nanos() + 1;
+    epoch + Duration::from_nanos((ticks * interval.as_nanos()) as u64)
+}
@@ -0,0 +1,61 @@
+/// ZDLE, which every ZMODEM header and escaped byte starts with. Five in
+/// a row cancel a transfer.
+const ZDLE: u8 = 0x18;
+
+/// Start of a hex header, followed by the frame type as two hex digits.
+const HEX_HEADER: &[u8] = b"**\x18B";
+
+/// Frame types a transfer starts with: `sz` sends ZRQINIT, `rz` ZRINIT.
+const ZRQINIT: &[u8] = b"00";
+const ZRINIT: &[u8] = b"01";
+/// Frame type both sides send to end the session.
+const ZFIN: &[u8] = b"08";
+
+const CANCEL: &[u8] = &[ZDLE; 5];
+
+/// Bytes kept from the end of a read, so a header split across reads is
+/// still seen: all of one but its last byte.
+const CARRY: usize = HEX_HEADER.len() + 1;
+
+/// Incremental detector for ZMODEM transfers (`sz`/`rz`) in a session's
+/// output. A transfer starts with the ZRQINIT or ZRINIT hex header and
+/// ends with ZFIN or a cancel.
+///
+/// Output without ZDLE is skipped after a single search.
+#[derive(Default)]
+pub struct ZmodemDetector {
+    active: bool,
+    carry: Vec<u8>,
+}
+
+impl ZmodemDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a transfer is running after `data`.
+    pub fn feed(&mut self, data: &[u8]) -> bool {
+        let mut scan = std::mem::take(&mut self.carry);
+        if !data.contains(&ZDLE) && !scan.contains(&ZDLE) {
+            scan.extend_from_slice(&data[data.len().saturating_sub(CARRY)..]);
+        } else {
+            scan.extend_from_slice(data);
+            let mut i = 0;
+            while let Some(offset) = scan[i..].iter().position(|&b| b == ZDLE) {
+                let at = i + offset;
+                if scan[at..].starts_with(CANCEL) {
+                    self.active = false;
+                } else if at >= 2 && scan[at - 2..].starts_with(HEX_HEADER) {
+                    match scan.get(at + 2..at + 4) {
+                        Some(ZRQINIT | ZRINIT) => self.active = true,
+                        Some(ZFIN) => self.active = false,
+                        _ => {}
+                    }
+                }
+                i = at + 1;
+            }
+        }
+        self.carry = scan.split_off(scan.len().saturating_sub(CARRY));
+        self.active
+    }
+}
@@ -0,0 +1,172 @@
+use super::protocol::{RemoteCommand, RemoteRequest};
+use super::socket_path;
+use crate::instance::absolute;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const USAGE: &str = "usage: abbyterm @ <command> [options]
+
+  new-tab [--cwd DIR] [--title TITLE] [COMMAND [ARGS...]]
+  send-text [--session ID] [--stdin | TEXT...]
+  get-text [--session ID]
+  list
+  focus [--session ID]
+  set-title [--session ID] TITLE...
+  close [--session ID]
+
+--session defaults to $ABBYTERM_SESSION_ID. TEXT understands \\n, \\r, \\t,
+\\e and \\\\.";
+
+/// Entry point of `abbyterm @ <command>`: sends one command to the running
+/// app over `$ABBYTERM_LISTEN_ON` and prints the result.
+pub fn run_remote(args: &[String]) -> Result<()> {
+    let request = RemoteRequest {
+        token: std::env::var("ABBYTERM_REMOTE_TOKEN").ok(),
+        command: parse(args)?,
+    };
+
+    let path = std::env::var_os("ABBYTERM_LISTEN_ON")
+        .map(PathBuf::from)
+        .or_else(socket_path)
+        .ok_or_else(|| anyhow!("Could not find home directory"))?;
+    let mut stream = UnixStream::connect(&path).map_err(|e| {
+        anyhow!(
+            "AbbyTerm is not listening on {} ({}); is [remote_control] enabled?",
+            path.display(),
+            e
+        )
+    })?;
+
+    let mut line = serde_json::to_string(&request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.is_empty() {
+        anyhow::bail!("AbbyTerm closed the connection without replying");
+    }
+
+    let result: Result<Value, String> = serde_json::from_str(&reply)?;
+    match result.map_err(|e| anyhow!(e))? {
+        Value::Null => {}
+        Value::String(text) => println!("{}", text),
+        value => println!("{}", serde_json::to_string_pretty(&value)?),
+    }
+    Ok(())
+}
+
+fn parse(args: &[String]) -> Result<RemoteCommand> {
+    let (name, rest) = args.split_first().ok_or_else(|| anyhow!(USAGE))?;
+
+    let mut session = None;
+    let mut cwd = None;
+    let mut title = None;
+    let mut stdin = false;
+    let mut positional = Vec::new();
+
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        // Options end at the first positional argument, so a new tab's
+        // command keeps its own flags.
+        if !positional.is_empty() {
+            positional.push(arg.clone());
+            continue;
+        }
+        match arg.as_str() {
+            "--" => positional.extend(iter.by_ref().cloned()),
+            "-s" | "--session" => session = Some(value(&mut iter, arg)?),
+            "--cwd" => cwd = Some(absolute(value(&mut iter, arg)?)),
+            "--title" => title = Some(value(&mut iter, arg)?),
+            "--stdin" => stdin = true,
+            "-h" | "--help" => anyhow::bail!(USAGE),
+            _ if arg.starts_with("--") => anyhow::bail!("Unknown option {}\n\n{}", arg, USAGE),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let session = || -> Result<Uuid> {
+        let id = session
+            .clone()
+            .or_else(|| std::env::var("ABBYTERM_SESSION_ID").ok())
+            .ok_or_else(|| anyhow!("No session given: pass --session or run inside AbbyTerm"))?;
+        Uuid::parse_str(&id).map_err(|e| anyhow!("Invalid session id {}: {}", id, e))
+    };
+
+    Ok(match name.as_str() {
+        "new-tab" => RemoteCommand::NewTab {
+            cwd,
+            title,
+            command: (!positional.is_empty()).then_some(positional),
+        },
+        "send-text" => {
+            let text = if stdin {
+                let mut text = String::new();
+                std::io::stdin().read_to_string(&mut text)?;
+                text
+            } else {
+                unescape(&positional.join(" "))
+            };
+            RemoteCommand::SendText {
+                session: session()?,
+                text,
+            }
+        }
+        "get-text" => RemoteCommand::GetText {
+            session: session()?,
+        },
+        "list" => RemoteCommand::List,
+        "focus" => RemoteCommand::Focus {
+            session: session()?,
+        },
+        "set-title" => {
+            let title = title
+                .or_else(|| (!positional.is_empty()).then(|| positional.join(" ")))
+                .ok_or_else(|| anyhow!("set-title needs a title"))?;
+            RemoteCommand::SetTitle {
+                session: session()?,
+                title,
+            }
+        }
+        "close" => RemoteCommand::Close {
+            session: session()?,
+        },
+        _ => anyhow::bail!("Unknown command {}\n\n{}", name, USAGE),
+    })
+}
+
+fn value<'a>(iter: &mut impl Iterator<Item = &'a String>, flag: &str) -> Result<String> {
+    iter.next()
+        .cloned()
+        .ok_or_else(|| anyhow!("{} needs a value", flag))
+}
+
+/// Expands the escapes scripts need to send keys: `\n`, `\r`, `\t`, `\e`
+/// and `\\`. Anything else is passed through unchanged.
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('e') => out.push('\x1b'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
@@ -0,0 +1,131 @@
+#[cfg(unix)]
+pub mod client;
+pub mod protocol;
+#[cfg(unix)]
+pub mod server;
+
+use crate::config::abbyterm_dir;
+use crate::pty::events::EventSink;
+use crate::pty::manager::PtyManager;
+use crate::pty::unix_pty::SpawnOptions;
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+#[cfg(unix)]
+pub use client::run_remote;
+#[cfg(unix)]
+pub use server::listen_for_commands;
+
+/// `[remote_control]` section of `~/.abbyterm/config.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RemoteControlConfig {
+    /// Accept `abbyterm @ <command>` over `~/.abbyterm/remote.sock`. The
+    /// socket is only reachable by the owner, and its path is exported to
+    /// sessions as `ABBYTERM_LISTEN_ON`.
+    pub enabled: bool,
+    /// Also require the `ABBYTERM_REMOTE_TOKEN` of a session opened by this
+    /// window, so only processes running inside AbbyTerm can drive it.
+    pub require_token: bool,
+}
+
+pub fn socket_path() -> Option<PathBuf> {
+    abbyterm_dir().map(|dir| dir.join("remote.sock"))
+}
+
+/// Remote-control state shared by the socket listener and the session
+/// commands: whether the socket is up, and the token issued to each session.
+#[derive(Default)]
+pub struct RemoteControl {
+    listening: AtomicBool,
+    tokens: Mutex<HashMap<String, Uuid>>,
+}
+
+impl RemoteControl {
+    pub fn set_listening(&self) {
+        self.listening.store(true, Ordering::Relaxed);
+    }
+
+    /// Creates a session that can reach the remote-control socket: its path
+    /// and a fresh token are added to the child environment.
+    pub async fn create_session(
+        &self,
+        manager: &PtyManager,
+        mut options: SpawnOptions,
+        events: EventSink,
+    ) -> Result<Uuid> {
+        self.forget_ended(manager).await;
+        let token = self.export_to(&mut options.env);
+        let id = manager.create_session(options, events).await?;
+        if let (Some(token), Ok(mut tokens)) = (token, self.tokens.lock()) {
+            tokens.insert(token, id);
+        }
+        Ok(id)
+    }
+
+    fn export_to(&self, env: &mut HashMap<String, String>) -> Option<String> {
+        if !self.listening.load(Ordering::Relaxed) {
+            return None;
+        }
+        let path = socket_path()?;
+        let token = Uuid::new_v4().simple().to_string();
+        env.insert("ABBYTERM_LISTEN_ON".to_string(), path.display().to_string());
+        env.insert("ABBYTERM_REMOTE_TOKEN".to_string(), token.clone());
+        Some(token)
+    }
+
+    /// Checks a request's token when `require_token` is set. Without it the
+    /// socket permissions are the only gate, and tokens are not looked at:
+    /// sessions kept by the daemon may carry tokens from an earlier window.
+    pub fn authorize(&self, token: Option<&str>, require_token: bool) -> Result<()> {
+        if !require_token {
+            return Ok(());
+        }
+        let token = token.ok_or_else(|| anyhow::anyhow!("A remote control token is required"))?;
+        let known = self
+            .tokens
+            .lock()
+            .map(|tokens| tokens.contains_key(token))
+            .unwrap_or(false);
+        if !known {
+            anyhow::bail!("Invalid remote control token");
+        }
+        Ok(())
+    }
+
+    /// Drops the tokens of sessions that are gone or whose child exited,
+    /// however they ended.
+    pub async fn forget_ended(&self, manager: &PtyManager) {
+        let Ok(sessions) = manager.list().await else {
+            return;
+        };
+        let live: HashSet<Uuid> = sessions
+            .iter()
+            .filter(|session| session.alive)
+            .filter_map(|session| Uuid::parse_str(&session.id).ok())
+            .collect();
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.retain(|_, session| live.contains(session));
+        }
+    }
+
+    /// Drops the tokens of a closed session.
+    pub fn forget(&self, id: Uuid) {
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.retain(|_, session| *session != id);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn run_remote(_args: &[String]) -> Result<()> {
+    anyhow::bail!("Remote control is not supported on this platform")
+}
+
+#[cfg(not(unix))]
+pub fn listen_for_commands(_app: tauri::AppHandle) {}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One `abbyterm @` invocation. The client writes it as a single JSON line
+/// and the app answers with one line holding a `Result<Value, String>`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteRequest {
+    /// The caller's `ABBYTERM_REMOTE_TOKEN`, if it runs inside a session.
+    pub token: Option<String>,
+    pub command: RemoteCommand,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+pub enum RemoteCommand {
+    /// Opens a local tab and returns its session id.
+    NewTab {
+        cwd: Option<String>,
+        title: Option<String>,
+        command: Option<Vec<String>>,
+    },
+    /// Writes `text` to the session's input.
+    SendText {
+        session: Uuid,
+        text: String,
+    },
+    /// Returns the session's recent output as plain text.
+    GetText {
+        session: Uuid,
+    },
+    /// Returns the open sessions.
+    List,
+    /// Switches to the tab holding the session and raises the window.
+    Focus {
+        session: Uuid,
+    },
+    SetTitle {
+        session: Uuid,
+        title: String,
+    },
+    /// Ends the session and closes its pane.
+    Close {
+        session: Uuid,
+    },
+}
+
+/// Payload of the `remote-*` events that ask the frontend to change tabs.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteTabEvent {
+    pub session_id: String,
+    pub title: Option<String>,
+}
@@ -0,0 +1,153 @@
+use super::protocol::{RemoteCommand, RemoteRequest, RemoteTabEvent};
+use super::{socket_path, RemoteControl};
+use crate::commands::pty_commands::session_options;
+use crate::config::AppConfig;
+use crate::instance::{bind_socket, show_main_window};
+use crate::pty::manager::PtyManager;
+use crate::pty::transcript::plain_text;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use uuid::Uuid;
+
+/// Grace period between SIGHUP and SIGKILL for `abbyterm @ close`.
+const CLOSE_GRACE: Duration = Duration::from_millis(250);
+
+/// Serves `abbyterm @` commands on `~/.abbyterm/remote.sock`. Sessions
+/// created after this learn the socket path through their environment.
+pub fn listen_for_commands(app: AppHandle) {
+    let Some(path) = socket_path() else {
+        return;
+    };
+
+    let listener = match bind_socket(&path).and_then(|listener| {
+        listener.set_nonblocking(true)?;
+        Ok(listener)
+    }) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Remote control unavailable: {}", err);
+            return;
+        }
+    };
+    app.state::<RemoteControl>().set_listening();
+
+    tauri::async_runtime::spawn(async move {
+        let listener = match UnixListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(err) => {
+                eprintln!("Remote control unavailable: {}", err);
+                return;
+            }
+        };
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tauri::async_runtime::spawn(handle_connection(app.clone(), stream));
+                }
+                Err(err) => eprintln!("Remote control accept failed: {}", err),
+            }
+        }
+    });
+}
+
+async fn handle_connection(app: AppHandle, stream: UnixStream) {
+    let (read, mut write) = stream.into_split();
+    let mut line = String::new();
+    let result = match BufReader::new(read).read_line(&mut line).await {
+        Ok(0) => return,
+        Ok(_) => handle_request(&app, &line).await.map_err(|e| e.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+
+    if let Ok(mut reply) = serde_json::to_string(&result) {
+        reply.push('\n');
+        let _ = write.write_all(reply.as_bytes()).await;
+    }
+}
+
+async fn handle_request(app: &AppHandle, line: &str) -> Result<Value> {
+    let request: RemoteRequest = serde_json::from_str(line)?;
+    let config = AppConfig::load().remote_control;
+    let remote = app.state::<RemoteControl>();
+    // A token stops working once its session has ended.
+    remote.forget_ended(&app.state::<PtyManager>()).await;
+    remote.authorize(request.token.as_deref(), config.require_token)?;
+    dispatch(app, request.command).await
+}
+
+async fn dispatch(app: &AppHandle, command: RemoteCommand) -> Result<Value> {
+    let manager = app.state::<PtyManager>();
+    let remote = app.state::<RemoteControl>();
+
+    Ok(match command {
+        RemoteCommand::NewTab {
+            cwd,
+            title,
+            command,
+        } => {
+            let mut command = command.unwrap_or_default().into_iter();
+            let shell = command.next();
+            let args: Vec<String> = command.collect();
+            let title = title.or_else(|| shell.clone());
+
+            let options =
+                session_options(shell, (!args.is_empty()).then_some(args), cwd, 80, 24, None);
+            let id = remote
+                .create_session(&manager, options, app.clone().into())
+                .await?;
+            emit_tab_event(app, "remote-new-tab", id, title)?;
+            json!(id.to_string())
+        }
+        RemoteCommand::SendText { session, text } => {
+            ensure_session(&manager, session).await?;
+            manager.write(session, text.as_bytes()).await?;
+            Value::Null
+        }
+        RemoteCommand::GetText { session } => {
+            let output = manager.replay(session).await?;
+            json!(plain_text(&output))
+        }
+        RemoteCommand::List => json!(manager.list().await?),
+        RemoteCommand::Focus { session } => {
+            ensure_session(&manager, session).await?;
+            emit_tab_event(app, "remote-focus", session, None)?;
+            show_main_window(app);
+            Value::Null
+        }
+        RemoteCommand::SetTitle { session, title } => {
+            ensure_session(&manager, session).await?;
+            emit_tab_event(app, "remote-set-title", session, Some(title))?;
+            Value::Null
+        }
+        RemoteCommand::Close { session } => {
+            ensure_session(&manager, session).await?;
+            manager.kill(session, CLOSE_GRACE).await?;
+            remote.forget(session);
+            emit_tab_event(app, "remote-close", session, None)?;
+            Value::Null
+        }
+    })
+}
+
+fn emit_tab_event(app: &AppHandle, event: &str, id: Uuid, title: Option<String>) -> Result<()> {
+    app.emit(
+        event,
+        RemoteTabEvent {
+            session_id: id.to_string(),
+            title,
+        },
+    )?;
+    Ok(())
+}
+
+async fn ensure_session(manager: &PtyManager, id: Uuid) -> Result<()> {
+    let id = id.to_string();
+    if !manager.list().await?.iter().any(|session| session.id == id) {
+        anyhow::bail!("Session not found");
+    }
+    Ok(())
+}
@@ -4,11 +4,89 @@ import { TabBar } from './components/TabBar/TabBar';
 import { TerminalContainer } from './components/Terminal/TerminalContainer';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { useGlobalShortcuts } from './hooks/useGlobalShortcuts';
-import { useTabStore } from './store/tabStore';
+import { useRemoteControl } from './hooks/useRemoteControl';
+import { useTabStore, tabStore } from './store/tabStore';
 import { useSettingsStore } from './store/settingsStore';
 import { v4 as uuidv4 } from 'uuid';
 
+// Tab requested on the command line with `-e`, `--cwd` or `--ssh`.
+interface LaunchRequest {
+  command: string[] | null;
+  cwd: string | null;
+  ssh: string | null;
+}
+
+// Opens the tab a launch asked for, or a default local shell for `null`.
+async function openLaunchTab(launch: LaunchRequest | null) {
+  const { addTab } = tabStore.getState();
+  const { defaultCwd } = useSettingsStore.getState().settings;
+
+  if (launch?.ssh) {
+    const sessionId = await invoke<string>('create_pty_session', {
+      shell: 'ssh',
+      args: ['-tt', launch.ssh, ...(launch.command ?? [])],
+      cwd: null,
+      cols: 80,
+      rows: 24,
+    });
+    const title = `SSH: ${launch.ssh}`;
+
+    addTab({
+      id: uuidv4(),
+      title,
+      sessionId,
+      type: 'ssh',
+      rootPane: {
+        type: 'terminal',
+        id: uuidv4(),
+        sessionId,
+        title,
+        tabType: 'ssh',
+      },
+    });
+    return;
+  }
+
+  const args = launch?.command;
+  let shell: string | undefined;
+  let shellArgs: string[] | undefined;
+
+  if (args && args.length === 1 && args[0].includes(' ')) {
+    const parts = args[0].split(' ');
+    shell = parts[0];
+    shellArgs = parts.slice(1);
+  } else if (args && args.length > 0) {
+    shell = args[0];
+    if (args.length > 1) {
+      shellArgs = args.slice(1);
+    }
+  }
+
+  const sessionId = await invoke<string>('create_pty_session', {
+    shell: shell || null,
+    args: shellArgs || null,
+    cwd: launch?.cwd || defaultCwd || null,
+    cols: 80,
+    rows: 24,
+  });
+
+  addTab({
+    id: uuidv4(),
+    title: shell || 'Local',
+    sessionId,
+    type: 'local',
+    rootPane: {
+      type: 'terminal',
+      id: uuidv4(),
+      sessionId,
+      title: shell || 'Local',
+      tabType: 'local',
+    },
+  });
+}
+
 function App() {
   const addTab = useTabStore((state) => state.addTab);
   const activeTabTitle = useTabStore((state) =>
@@ -17,6 +95,7 @@ function App() {
   const settings = useSettingsStore((state) => state.settings);
   const initialized = useRef(false);
   useGlobalShortcuts();
+  useRemoteControl();
 
   useEffect(() => {
     // With `decorations: false`, many Linux compositors don't draw a shadow by default.
@@ -174,48 +253,53 @@ function App() {
           console.warn('Failed to check docker availability:', e);
         }
 
-        const args = await invoke<string[] | null>('get_initial_args');
+        const launch = await invoke<LaunchRequest | null>('get_launch_request');
 
-        // Auto-start only when requested by command args or app settings.
-        if ((!args || args.length === 0) && !settings.autoStartLocalTerminal) {
-          return;
+        // Sessions that outlived the previous window (kept by the session
+        // daemon, or across a webview reload) are reopened as tabs, one per
+        // session since the split layout isn't kept. Ended ones are cleaned up.
+        const sessions = await invoke<{ id: string; command: string; args: string[]; alive: boolean }[]>('list_pty_sessions').catch(() => []);
+        const running = sessions.filter((session) => session.alive);
+        for (const session of sessions) {
+          if (!session.alive) {
+            invoke('pty_kill', { sessionId: session.id }).catch(() => {});
+          }
+        }
+        for (const session of running) {
+          // Sessions the app starts itself: `ssh -tt <destination> ...` and
+          // `abbyterm --ratel <host:port>`.
+          const program = session.command.split(/[\\/]/).pop() || 'Local';
+          let type: 'local' | 'ssh' | 'plugin' = 'local';
+          let title = program;
+          if (session.args[0] === '--ratel') {
+            type = 'plugin';
+            title = `Ratel: ${session.args[1]}`;
+          } else if (program.replace(/\.exe$/, '') === 'ssh') {
+            type = 'ssh';
+            title = `SSH: ${session.args.find((arg) => !arg.startsWith('-')) ?? ''}`;
+          }
+          addTab({
+            id: uuidv4(),
+            title,
+            sessionId: session.id,
+            type,
+            rootPane: {
+              type: 'terminal',
+              id: uuidv4(),
+              sessionId: session.id,
+              title,
+              tabType: type,
+              reattached: true,
+            },
+          });
         }
 
-        let shell: string | undefined;
-        let shellArgs: string[] | undefined;
-
-        if (args && args.length === 1 && args[0].includes(' ')) {
-            const parts = args[0].split(' ');
-            shell = parts[0];
-            shellArgs = parts.slice(1);
-        } else if (args && args.length > 0) {
-            shell = args[0];
-            if (args.length > 1) {
-                shellArgs = args.slice(1);
-            }
+        // Auto-start only when requested by command args or app settings.
+        if (!launch && (running.length > 0 || !settings.autoStartLocalTerminal)) {
+          return;
         }
 
-        const sessionId = await invoke<string>('create_pty_session', {
-          shell: shell || null,
-          args: shellArgs || null,
-          cwd: settings.defaultCwd || null,
-          cols: 80,
-          rows: 24,
-        });
-
-        addTab({
-          id: uuidv4(),
-          title: shell || 'Local',
-          sessionId,
-          type: 'local',
-          rootPane: {
-            type: 'terminal',
-            id: uuidv4(),
-            sessionId,
-            title: shell || 'Local',
-            tabType: 'local',
-          },
-        });
+        await openLaunchTab(launch);
       } catch (error) {
         console.error('Failed to initialize terminal:', error);
       }
@@ -226,6 +310,26 @@ function App() {
     return;
   }, []);
 
+  // Later `abbyterm -e/--cwd/--ssh` invocations are forwarded here by the
+  // single-instance listener. Those that arrived before the listener was
+  // registered were queued by the backend.
+  useEffect(() => {
+    const open = (launch: LaunchRequest) => {
+      openLaunchTab(launch).catch((error) => {
+        console.error('Failed to open requested tab:', error);
+      });
+    };
+    const unlistenPromise = listen<LaunchRequest>('launch-request', (event) => open(event.payload));
+    unlistenPromise
+      .then(() => invoke<LaunchRequest[]>('take_queued_launches'))
+      .then((queued) => queued.forEach(open))
+      .catch(() => {});
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten()).catch(() => {});
+    };
+  }, []);
+
   return (
     <div className="h-screen w-screen flex items-center justify-center">
       <div className="h-full w-full flex flex-col overflow-hidden app-bg border border-solid app-border">
@@ -1,8 +1,32 @@
+import { useEffect, useState } from 'react';
 import { X } from 'lucide-react';
 import { useTabStore } from '@/store/tabStore';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { TabContextMenu } from './TabContextMenu';
 
+interface ResourceUsage {
+  session_id: string;
+  cpu_percent: number;
+  rss_bytes: number;
+  threads: number;
+  processes: number;
+}
+
+// Above this much CPU (percent of one core, summed over the tab's panes)
+// the tab shows a load badge.
+const BUSY_CPU_PERCENT = 50;
+
+const formatBytes = (bytes: number) => {
+  if (bytes >= 1024 * 1024 * 1024) return `${(bytes / (1024 * 1024 * 1024)).toFixed(1)} GB`;
+  return `${Math.round(bytes / (1024 * 1024))} MB`;
+};
+
+interface MonitorAlert {
+  kind: 'activity' | 'silence' | 'trigger';
+  detail: string;
+}
+
 interface TabProps {
   id: string;
   title: string;
@@ -11,6 +35,84 @@ interface TabProps {
 
 export function Tab({ id, title, isActive }: TabProps) {
   const { setActiveTab, removeTab } = useTabStore();
+  const [usage, setUsage] = useState<Record<string, ResourceUsage>>({});
+  const [monitorAlert, setMonitorAlert] = useState<MonitorAlert | null>(null);
+
+  // Joined so the effect only re-runs when panes are added or closed.
+  const sessionKey = useTabStore((state) => {
+    const tab = state.tabs.find((t) => t.id === id);
+    if (!tab) return '';
+    const collectSessionIds = (pane: any): string[] => {
+      if (pane.type === 'terminal') {
+        return [pane.sessionId];
+      } else if (pane.type === 'split') {
+        return pane.children.flatMap((child: any) => collectSessionIds(child));
+      }
+      return [];
+    };
+    return collectSessionIds(tab.rootPane).join(',');
+  });
+
+  useEffect(() => {
+    const sessionIds = sessionKey ? sessionKey.split(',') : [];
+    if (sessionIds.length === 0) return;
+
+    const unlistenPromise = listen<ResourceUsage>('session-resource-usage', (event) => {
+      if (!sessionIds.includes(event.payload.session_id)) return;
+      setUsage((prev) => ({ ...prev, [event.payload.session_id]: event.payload }));
+    });
+
+    for (const sid of sessionIds) {
+      invoke('subscribe_session_resource_usage', { sessionId: sid }).catch((err) => {
+        console.error('Failed to subscribe to resource usage:', sid, err);
+      });
+    }
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten()).catch(() => {});
+      for (const sid of sessionIds) {
+        invoke('unsubscribe_session_resource_usage', { sessionId: sid }).catch(() => {});
+      }
+      setUsage({});
+    };
+  }, [sessionKey]);
+
+  // Activity, silence and triggers only matter for tabs the user isn't
+  // looking at. A trigger outranks the other two until the tab is shown.
+  useEffect(() => {
+    if (isActive) {
+      setMonitorAlert(null);
+      return;
+    }
+
+    const sessionIds = sessionKey ? sessionKey.split(',') : [];
+    const unlistenPromises = [
+      listen<{ session_id: string }>('session-activity', (event) => {
+        if (!sessionIds.includes(event.payload.session_id)) return;
+        setMonitorAlert((prev) => (prev?.kind === 'trigger' ? prev : { kind: 'activity', detail: 'New output' }));
+      }),
+      listen<{ session_id: string }>('session-silence', (event) => {
+        if (!sessionIds.includes(event.payload.session_id)) return;
+        setMonitorAlert((prev) => (prev?.kind === 'trigger' ? prev : { kind: 'silence', detail: 'Output stopped' }));
+      }),
+      listen<{ session_id: string; line: string }>('session-trigger', (event) => {
+        if (!sessionIds.includes(event.payload.session_id)) return;
+        setMonitorAlert({ kind: 'trigger', detail: event.payload.line });
+      }),
+    ];
+
+    return () => {
+      unlistenPromises.forEach((promise) => promise.then((unlisten) => unlisten()).catch(() => {}));
+    };
+  }, [isActive, sessionKey]);
+
+  const readings = Object.values(usage);
+  const cpuPercent = readings.reduce((sum, u) => sum + u.cpu_percent, 0);
+  const rssBytes = readings.reduce((sum, u) => sum + u.rss_bytes, 0);
+  const isBusy = cpuPercent >= BUSY_CPU_PERCENT;
+  const usageTitle = readings.length > 0
+    ? `CPU ${Math.round(cpuPercent)}% · Memory ${formatBytes(rssBytes)}`
+    : undefined;
 
   const handleClose = async (e: React.MouseEvent) => {
     e.stopPropagation();
@@ -57,9 +159,29 @@ export function Tab({ id, title, isActive }: TabProps) {
           }
         `}
         onClick={() => setActiveTab(id)}
-        title={title}
+        title={usageTitle ? `${title}\n${usageTitle}` : title}
       >
+        {monitorAlert && (
+          <span
+            className={`w-1.5 h-1.5 rounded-full flex-shrink-0 ${
+              monitorAlert.kind === 'trigger'
+                ? 'bg-red-500'
+                : monitorAlert.kind === 'activity'
+                  ? 'bg-blue-500'
+                  : 'bg-yellow-500'
+            }`}
+            title={monitorAlert.detail}
+          />
+        )}
         <span className="text-sm truncate flex-1">{title}</span>
+        {isBusy && (
+          <span
+            className="text-[10px] px-1 rounded app-surface-2 text-yellow-500 flex-shrink-0 tabular-nums"
+            title={usageTitle}
+          >
+            {Math.round(cpuPercent)}%
+          </span>
+        )}
         <button
           onClick={handleClose}
           className="app-hover-2 p-0.5 transition-colors flex-shrink-0"
@@ -10,13 +10,17 @@ import {
   ContextMenuSeparator,
 } from '@/components/Shared/ContextMenu';
 
+// Quiet periods behind the activity and silence monitors.
+const ACTIVITY_AFTER_MS = 2000;
+const SILENCE_AFTER_MS = 30000;
+
 interface TabContextMenuProps {
   tabId: string;
   children: ReactNode;
 }
 
 export function TabContextMenu({ tabId, children }: TabContextMenuProps) {
-  const { tabs, removeTab } = useTabStore();
+  const { tabs, removeTab, updateTab } = useTabStore();
   const [copyStatus, setCopyStatus] = useState<'idle' | 'copying' | 'success' | 'error'>('idle');
 
   const currentTab = tabs.find((t) => t.id === tabId);
@@ -98,6 +102,68 @@ export function TabContextMenu({ tabId, children }: TabContextMenuProps) {
     }
   };
 
+  const handleToggleSynchronize = async () => {
+    if (!currentTab) return;
+
+    const collectSessionIds = (pane: any): string[] => {
+      if (pane.type === 'terminal') {
+        return [pane.sessionId];
+      } else if (pane.type === 'split') {
+        return pane.children.flatMap((child: any) => collectSessionIds(child));
+      }
+      return [];
+    };
+    const sessionIds = collectSessionIds(currentTab.rootPane);
+
+    if (currentTab.inputGroupId) {
+      for (const sid of sessionIds) {
+        try {
+          await invoke('remove_from_group', { groupId: currentTab.inputGroupId, sessionId: sid });
+        } catch {
+          // Already dropped from the group, e.g. because its shell exited.
+        }
+      }
+      updateTab(tabId, { inputGroupId: undefined });
+      return;
+    }
+
+    try {
+      const groupId = await invoke<string>('create_input_group', { sessionIds });
+      updateTab(tabId, { inputGroupId: groupId });
+    } catch (err) {
+      console.error('Failed to synchronize panes:', err);
+    }
+  };
+
+  const handleToggleMonitor = async (kind: 'activity' | 'silence') => {
+    if (!currentTab) return;
+
+    const collectSessionIds = (pane: any): string[] => {
+      if (pane.type === 'terminal') {
+        return [pane.sessionId];
+      } else if (pane.type === 'split') {
+        return pane.children.flatMap((child: any) => collectSessionIds(child));
+      }
+      return [];
+    };
+
+    const monitorActivity = kind === 'activity' ? !currentTab.monitorActivity : !!currentTab.monitorActivity;
+    const monitorSilence = kind === 'silence' ? !currentTab.monitorSilence : !!currentTab.monitorSilence;
+
+    for (const sid of collectSessionIds(currentTab.rootPane)) {
+      try {
+        await invoke('set_session_monitor', {
+          sessionId: sid,
+          activityAfterMs: monitorActivity ? ACTIVITY_AFTER_MS : null,
+          silenceAfterMs: monitorSilence ? SILENCE_AFTER_MS : null,
+        });
+      } catch (err) {
+        console.error('Failed to set session monitor:', sid, err);
+      }
+    }
+    updateTab(tabId, { monitorActivity, monitorSilence });
+  };
+
   const handleCopyWorkingDirectory = async () => {
     setCopyStatus('copying');
     try {
@@ -170,6 +236,18 @@ export function TabContextMenu({ tabId, children }: TabContextMenuProps) {
         <ContextMenuItem onSelect={handleCopyWorkingDirectory} disabled={copyStatus === 'copying'}>
           {getCopyButtonText()}
         </ContextMenuItem>
+        <ContextMenuItem
+          onSelect={handleToggleSynchronize}
+          disabled={!currentTab || (currentTab.rootPane.type !== 'split' && !currentTab.inputGroupId)}
+        >
+          {currentTab?.inputGroupId ? 'Stop Synchronizing Panes' : 'Synchronize Panes'}
+        </ContextMenuItem>
+        <ContextMenuItem onSelect={() => handleToggleMonitor('activity')} disabled={!currentTab}>
+          {currentTab?.monitorActivity ? 'Stop Monitoring Activity' : 'Monitor Activity'}
+        </ContextMenuItem>
+        <ContextMenuItem onSelect={() => handleToggleMonitor('silence')} disabled={!currentTab}>
+          {currentTab?.monitorSilence ? 'Stop Monitoring Silence' : 'Monitor Silence'}
+        </ContextMenuItem>
         <ContextMenuSeparator />
         <ContextMenuItem onSelect={handleClose}>
           Close
@@ -111,7 +111,14 @@ export function SplitPane({ pane, isActive, tabId }: SplitPaneProps) {
 
     return (
       <div className="w-full h-full border app-border">
-        <Terminal sessionId={pane.sessionId} isActive={isActive} tabId={tabId} paneId={pane.id} isPrimaryPane={isPrimaryPane} />
+        <Terminal
+          sessionId={pane.sessionId}
+          isActive={isActive}
+          tabId={tabId}
+          paneId={pane.id}
+          isPrimaryPane={isPrimaryPane}
+          reattached={pane.reattached}
+        />
       </div>
     );
   }
@@ -157,6 +164,7 @@ export function SplitPane({ pane, isActive, tabId }: SplitPaneProps) {
                     isActive={isActive}
                     tabId={tabId}
                     paneId={child.id}
+                    reattached={child.reattached}
                   />
                 ) : (
                   <SplitPane pane={child} isActive={isActive} tabId={tabId} />
@@ -2,7 +2,7 @@ import { useEffect, useRef, useState } from 'react';
 import { Terminal as XTerm } from 'xterm';
 import { FitAddon } from 'xterm-addon-fit';
 import { WebLinksAddon } from 'xterm-addon-web-links';
-import { invoke } from '@tauri-apps/api/core';
+import { Channel, invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { open as openExternal } from '@tauri-apps/plugin-shell';
 import { open as openFileDialog } from '@tauri-apps/plugin-dialog';
@@ -25,6 +25,42 @@ type ZmodemUploadFile = {
   last_modified_ms: number;
 };
 
+type PtyExitInfo = {
+  success: boolean;
+  exit_code: number | null;
+  signal: number | null;
+  signal_name: string | null;
+  core_dumped: boolean;
+};
+
+// Rendered output is acknowledged to the backend in batches of this size.
+// Must stay below the backend's low watermark (256 KB), or a paused session
+// could wait for an acknowledgement that is never sent.
+const PTY_ACK_BYTES = 64 * 1024;
+
+const describePtyExit = (info: PtyExitInfo | null | undefined) => {
+  if (!info) return 'Process exited';
+  if (info.signal_name) {
+    return `Process terminated by ${info.signal_name}${info.core_dumped ? ' (core dumped)' : ''}`;
+  }
+  if (info.exit_code !== null && info.exit_code !== 0) {
+    return `Process exited with code ${info.exit_code}`;
+  }
+  return 'Process exited';
+};
+
+// Cell size in CSS pixels, passed along with the grid size so programs that
+// draw images can read the terminal's pixel size (TIOCGWINSZ).
+const getPtyCellSize = (term: XTerm) => {
+  const dims = (term as any)?._core?._renderService?.dimensions;
+  const width = dims?.css?.cell?.width ?? dims?.actualCellWidth;
+  const height = dims?.css?.cell?.height ?? dims?.actualCellHeight;
+  if (typeof width === 'number' && width > 0 && typeof height === 'number' && height > 0) {
+    return { cellWidth: Math.round(width), cellHeight: Math.round(height) };
+  }
+  return {};
+};
+
 type ZmodemTransferProgress = {
   fileName: string;
   fileIndex: number;
@@ -48,7 +84,7 @@ const formatBytes = (bytes: number) => {
   return `${value.toFixed(precision)} ${units[unitIndex]}`;
 };
 
-export function Terminal({ sessionId, isActive, tabId, paneId, isPrimaryPane }: TerminalProps) {
+export function Terminal({ sessionId, isActive, tabId, paneId, isPrimaryPane, reattached }: TerminalProps) {
   const containerRef = useRef<HTMLDivElement>(null);
   const terminalRef = useRef<HTMLDivElement>(null);
   const xtermRef = useRef<XTerm | null>(null);
@@ -206,6 +242,7 @@ export function Terminal({ sessionId, isActive, tabId, paneId, isPrimaryPane }:
           sessionId,
           cols: term.cols,
           rows: term.rows,
+          ...getPtyCellSize(term),
         }).catch((err) => console.error('Failed to resize PTY after settings change:', err));
       } catch (e) {
         console.error('Failed to fit terminal after settings change:', e);
@@ -227,6 +264,7 @@ export function Terminal({ sessionId, isActive, tabId, paneId, isPrimaryPane }:
                 sessionId,
                 cols: term.cols,
                 rows: term.rows,
+                ...getPtyCellSize(term),
               }).catch((err) => console.error('Failed to resize PTY on activation:', err));
             }
           } catch (e) {
@@ -238,6 +276,11 @@ export function Terminal({ sessionId, isActive, tabId, paneId, isPrimaryPane }:
     }
   }, [isActive, sessionId]);
 
+  // Background tabs get their output in one batch when shown again
+  useEffect(() => {
+    invoke('set_session_visibility', { sessionId, visible: isActive }).catch(() => {});
+  }, [isActive, sessionId]);
+
   // Start/stop CWD polling when tab becomes active/inactive
   useEffect(() => {
     if (xtermRef.current) {
@@ -1084,6 +1127,7 @@ export function Terminal({ sessionId, isActive, tabId, paneId, isPrimaryPane }:
           sessionId,
           cols: term.cols,
           rows: term.rows,
+          ...getPtyCellSize(term),
         }).catch((err) => {
           console.error('Failed to resize PTY:', err);
         });
@@ -1107,27 +1151,42 @@ export function Terminal({ sessionId, isActive, tabId, paneId, isPrimaryPane }:
       handleResize();
     }, 100);
 
-    // Listen for PTY output
-    const unlistenPromise = listen<number[] | string>(`pty-output-${sessionId}`, (event) => {
+    // PTY output arrives as binary messages on a channel. Sessions adopted
+    // from an earlier window already printed output; the backend sends
+    // their scrollback first, ahead of anything new.
+    // The backend stops reading from the PTY while too much output is
+    // unacknowledged, so a flood can't outrun xterm.js.
+    let unackedBytes = 0;
+    const ackOutput = (bytes: number) => {
+      unackedBytes += bytes;
+      if (unackedBytes >= PTY_ACK_BYTES) {
+        invoke('pty_ack_output', { sessionId, bytes: unackedBytes }).catch(() => {});
+        unackedBytes = 0;
+      }
+    };
+
+    const outputChannel = new Channel<ArrayBuffer | number[]>();
+    outputChannel.onmessage = (payload) => {
       if (term && !(term as any).isDisposed) {
-        const chunk =
-          typeof event.payload === 'string'
-            ? new TextEncoder().encode(event.payload)
-            : Uint8Array.from(event.payload);
+        const chunk = payload instanceof ArrayBuffer ? new Uint8Array(payload) : Uint8Array.from(payload);
         const normalizedChunk = normalizeZmodemInboundBytes(chunk);
 
         if (zmodemSentry) {
           zmodemSentry.consume(normalizedChunk);
+          ackOutput(chunk.length);
         } else {
-          term.write(normalizedChunk);
+          term.write(normalizedChunk, () => ackOutput(chunk.length));
         }
       }
+    };
+    invoke('attach_pty_output', { sessionId, replay: !!reattached, onOutput: outputChannel }).catch((err) => {
+      console.error('Failed to attach to PTY output:', err);
     });
 
     // Listen for PTY exit
-    const unlistenExitPromise = listen(`pty-exit-${sessionId}`, () => {
+    const unlistenExitPromise = listen<PtyExitInfo | null>(`pty-exit-${sessionId}`, (event) => {
       if (!term) return;
-      term.write('\r\n\r\n[Process exited]\r\n');
+      term.write(`\r\n\r\n[${describePtyExit(event.payload)}]\r\n`);
       term.options.cursorBlink = false;
     });
 
@@ -1168,7 +1227,7 @@ export function Terminal({ sessionId, isActive, tabId, paneId, isPrimaryPane }:
 
       resizeObserver.disconnect();
 
-      unlistenPromise.then((unlisten) => unlisten()).catch(() => {});
+      outputChannel.onmessage = () => {};
       unlistenExitPromise.then((unlisten) => unlisten()).catch(() => {});
 
       // Safely dispose terminal
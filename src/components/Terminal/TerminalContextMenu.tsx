@@ -64,6 +64,13 @@ export function TerminalContextMenu({
         return pane;
       };
       updateTab(tabId, { rootPane: updatePane(currentTab.rootPane) });
+
+      // New panes of a synchronized tab receive the same input.
+      if (currentTab.inputGroupId) {
+        invoke('add_to_group', { groupId: currentTab.inputGroupId, sessionId }).catch((err) => {
+          console.warn('Failed to add the new pane to its input group:', err);
+        });
+      }
     }
   };
 
@@ -0,0 +1,79 @@
+import { useEffect } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { tabStore } from '@/store/tabStore';
+import { Pane } from '@/types/tab';
+import { v4 as uuidv4 } from 'uuid';
+
+// Payload of the `remote-*` events sent for `abbyterm @` commands.
+interface RemoteTabEvent {
+  session_id: string;
+  title: string | null;
+}
+
+// Finds the tab and pane showing a session.
+const findSession = (sessionId: string) => {
+  const findPane = (pane: Pane): string | null => {
+    if (pane.type === 'terminal') {
+      return pane.sessionId === sessionId ? pane.id : null;
+    }
+    for (const child of pane.children) {
+      const found = findPane(child);
+      if (found) return found;
+    }
+    return null;
+  };
+
+  for (const tab of tabStore.getState().tabs) {
+    const paneId = findPane(tab.rootPane);
+    if (paneId) return { tab, paneId };
+  }
+  return null;
+};
+
+// Applies the tab changes requested through the remote-control socket.
+export function useRemoteControl() {
+  useEffect(() => {
+    const unlistenPromises = [
+      listen<RemoteTabEvent>('remote-new-tab', (event) => {
+        const { session_id: sessionId, title } = event.payload;
+        // The session was started before this tab existed; replay whatever
+        // it printed in the meantime.
+        tabStore.getState().addTab({
+          id: uuidv4(),
+          title: title || 'Local',
+          sessionId,
+          type: 'local',
+          rootPane: {
+            type: 'terminal',
+            id: uuidv4(),
+            sessionId,
+            title: title || 'Local',
+            tabType: 'local',
+            reattached: true,
+          },
+        });
+      }),
+      listen<RemoteTabEvent>('remote-focus', (event) => {
+        const found = findSession(event.payload.session_id);
+        if (found) tabStore.getState().setActiveTab(found.tab.id);
+      }),
+      listen<RemoteTabEvent>('remote-set-title', (event) => {
+        const found = findSession(event.payload.session_id);
+        if (found && event.payload.title) {
+          tabStore.getState().updateTab(found.tab.id, { title: event.payload.title });
+        }
+      }),
+      listen<RemoteTabEvent>('remote-close', (event) => {
+        // The backend already ended the session.
+        const found = findSession(event.payload.session_id);
+        if (found) tabStore.getState().closePane(found.tab.id, found.paneId);
+      }),
+    ];
+
+    return () => {
+      for (const unlistenPromise of unlistenPromises) {
+        unlistenPromise.then((unlisten) => unlisten()).catch(() => {});
+      }
+    };
+  }, []);
+}
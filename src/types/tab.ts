@@ -42,6 +42,7 @@ export interface TerminalPane {
   sessionId: string;
   title: string;
   tabType: 'local' | 'ssh' | 'plugin';
+  reattached?: boolean;
 }
 
 export interface SplitPane {
@@ -60,6 +61,9 @@ export interface Tab {
   sessionId: string; // Primary sessionId for backward compatibility
   type: 'local' | 'ssh' | 'plugin'; // Type for backward compatibility
   rootPane: Pane;
+  inputGroupId?: string; // Set while typing into one pane types into all of them
+  monitorActivity?: boolean; // Flag the tab when a quiet pane starts printing
+  monitorSilence?: boolean; // Flag the tab when its panes stop printing
 }
 
 export interface TabStore {
@@ -4,6 +4,8 @@ export interface TerminalProps {
   tabId?: string;
   paneId?: string;
   isPrimaryPane?: boolean;
+  /** The session outlived an earlier window; replay its output on mount. */
+  reattached?: boolean;
 }
 
 export interface TerminalTheme {